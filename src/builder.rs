@@ -0,0 +1,163 @@
+use crossbeam_channel::{Receiver, Sender};
+use rustafarian_shared::messages::commander_messages::{
+    SimControllerCommand, SimControllerResponseWrapper,
+};
+use rustafarian_shared::messages::general_messages::ServerType;
+use std::collections::HashMap;
+use wg_2024::packet::Packet;
+
+use crate::config::ContentServerConfig;
+use crate::content_server::{ContentServer, ContentServerOptions};
+use crate::error::ContentServerError;
+use crate::messages::ContentEvent;
+
+/// Creates a `ContentServer` step by step, every setting not given keeps the default
+/// of `ContentServerConfig`, only the channels are required
+/// ```ignore
+/// let server = ContentServerBuilder::new(3)
+///     .server_type(ServerType::Media)
+///     .media_directory("media")
+///     .receiver(receiver)
+///     .controller(controller_receiver, controller_sender)
+///     .build()?;
+/// ```
+#[derive(Default)]
+pub struct ContentServerBuilder {
+    config: ContentServerConfig,
+    senders: HashMap<u8, Sender<Packet>>,
+    receiver: Option<Receiver<Packet>>,
+    controller_receiver: Option<Receiver<SimControllerCommand>>,
+    controller_sender: Option<Sender<SimControllerResponseWrapper>>,
+    content_listener: Option<Sender<ContentEvent>>,
+}
+
+impl ContentServerBuilder {
+    /// Starts the builder of the server with the given id
+    pub fn new(server_id: u8) -> Self {
+        Self::from_config(ContentServerConfig {
+            server_id,
+            ..ContentServerConfig::default()
+        })
+    }
+
+    /// Starts the builder of the server described by the configuration
+    pub fn from_config(config: ContentServerConfig) -> Self {
+        ContentServerBuilder {
+            config,
+            ..ContentServerBuilder::default()
+        }
+    }
+
+    /// Sets the type of content served
+    #[must_use]
+    pub fn server_type(mut self, server_type: ServerType) -> Self {
+        self.config.server_type = server_type;
+        self
+    }
+
+    /// Sets the directory of the text files, relative to the current directory
+    #[must_use]
+    pub fn file_directory(mut self, directory: &str) -> Self {
+        self.config.file_directory = directory.to_string();
+        self
+    }
+
+    /// Sets the directory of the media files, relative to the current directory
+    #[must_use]
+    pub fn media_directory(mut self, directory: &str) -> Self {
+        self.config.media_directory = directory.to_string();
+        self
+    }
+
+    /// Sets whether the server writes its log
+    #[must_use]
+    pub fn debug(mut self, debug: bool) -> Self {
+        self.config.debug = debug;
+        self
+    }
+
+    /// Replaces all the options
+    #[must_use]
+    pub fn options(mut self, options: ContentServerOptions) -> Self {
+        self.config.options = options;
+        self
+    }
+
+    /// Changes some of the options, e.g. `.configure(|options| options.hybrid = true)`
+    #[must_use]
+    pub fn configure(mut self, change: impl FnOnce(&mut ContentServerOptions)) -> Self {
+        change(&mut self.config.options);
+        self
+    }
+
+    /// Adds the channel to a neighbor drone
+    #[must_use]
+    pub fn sender(mut self, drone_id: u8, sender: Sender<Packet>) -> Self {
+        self.senders.insert(drone_id, sender);
+        self
+    }
+
+    /// Adds the channels to the neighbor drones, by drone id
+    #[must_use]
+    pub fn senders(mut self, senders: HashMap<u8, Sender<Packet>>) -> Self {
+        self.senders.extend(senders);
+        self
+    }
+
+    /// Sets the channel where the server receives the packets of the drones
+    #[must_use]
+    pub fn receiver(mut self, receiver: Receiver<Packet>) -> Self {
+        self.receiver = Some(receiver);
+        self
+    }
+
+    /// Sets the channels to receive the commands of the simulation controller and to answer it
+    #[must_use]
+    pub fn controller(
+        mut self,
+        receiver: Receiver<SimControllerCommand>,
+        sender: Sender<SimControllerResponseWrapper>,
+    ) -> Self {
+        self.controller_receiver = Some(receiver);
+        self.controller_sender = Some(sender);
+        self
+    }
+
+    /// Sets the channel where the server sends a `ContentEvent` when its content changes
+    #[must_use]
+    pub fn content_listener(mut self, listener: Sender<ContentEvent>) -> Self {
+        self.content_listener = Some(listener);
+        self
+    }
+
+    /// Creates the server and loads its content
+    /// # Errors
+    /// Returns `MissingChannel` if the packet or controller channels were not given,
+    /// or the error raised loading the content
+    pub fn build(self) -> Result<ContentServer, ContentServerError> {
+        let receiver = self
+            .receiver
+            .ok_or(ContentServerError::MissingChannel("packet"))?;
+        let controller_receiver = self
+            .controller_receiver
+            .ok_or(ContentServerError::MissingChannel("controller command"))?;
+        let controller_sender = self
+            .controller_sender
+            .ok_or(ContentServerError::MissingChannel("controller response"))?;
+        let config = self.config;
+        let mut server = ContentServer::with_options(
+            config.server_id,
+            self.senders,
+            receiver,
+            controller_receiver,
+            controller_sender,
+            &config.file_directory,
+            &config.media_directory,
+            config.server_type,
+            config.debug,
+            config.options,
+        )?;
+        server.content_listener = self.content_listener;
+        Ok(server)
+    }
+}
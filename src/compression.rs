@@ -0,0 +1,62 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use flate2::read::{DeflateDecoder, GzDecoder};
+use flate2::write::{DeflateEncoder, GzEncoder};
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use std::io::{self, Read, Write};
+
+/// Compression a client can ask for the response to its request
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ContentEncoding {
+    Gzip,
+    Deflate,
+}
+
+/// Compresses the bytes with the encoding
+/// # Errors
+/// Returns the error raised by the encoder
+pub fn compress(data: &[u8], encoding: ContentEncoding) -> io::Result<Vec<u8>> {
+    match encoding {
+        ContentEncoding::Gzip => {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(data)?;
+            encoder.finish()
+        }
+        ContentEncoding::Deflate => {
+            let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(data)?;
+            encoder.finish()
+        }
+    }
+}
+
+/// Decompresses bytes compressed with the encoding
+/// # Errors
+/// Returns the error raised by the decoder, e.g. if the bytes are not valid for the encoding
+pub fn decompress(data: &[u8], encoding: ContentEncoding) -> io::Result<Vec<u8>> {
+    let mut decompressed = Vec::new();
+    match encoding {
+        ContentEncoding::Gzip => GzDecoder::new(data).read_to_end(&mut decompressed)?,
+        ContentEncoding::Deflate => DeflateDecoder::new(data).read_to_end(&mut decompressed)?,
+    };
+    Ok(decompressed)
+}
+
+/// Compresses a serialized response and encodes it in base64, so it can be sent inside JSON
+/// # Errors
+/// Returns the error raised by the encoder
+pub fn encode_message(message: &str, encoding: ContentEncoding) -> io::Result<String> {
+    Ok(STANDARD.encode(compress(message.as_bytes(), encoding)?))
+}
+
+/// Returns the serialized response carried by an `Encoded` response
+/// # Errors
+/// Returns `InvalidData` if the payload is not valid base64 or UTF-8, or the error raised by the decoder
+pub fn decode_message(data: &str, encoding: ContentEncoding) -> io::Result<String> {
+    let compressed = STANDARD
+        .decode(data)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    String::from_utf8(decompress(&compressed, encoding)?)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
@@ -0,0 +1,118 @@
+use rustafarian_shared::messages::general_messages::ServerType;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+use crate::content_server::ContentServerOptions;
+use crate::error::ContentServerError;
+
+/// Everything needed to create a `ContentServer` apart from its channels,
+/// it can be read from a TOML or JSON file where every field is optional, e.g.
+/// ```toml
+/// server_id = 3
+/// server_type = "Media"
+/// media_directory = "media"
+/// media_selection = { FirstSorted = 5 }
+/// log_level = "Info"
+/// flood_interval_ms = 500
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ContentServerConfig {
+    pub server_id: u8,
+    pub server_type: ServerType,
+    /// Directory of the text files, relative to the current directory
+    pub file_directory: String,
+    /// Directory of the media files, relative to the current directory
+    pub media_directory: String,
+    /// Whether the server writes its log
+    pub debug: bool,
+    #[serde(flatten)]
+    pub options: ContentServerOptions,
+}
+
+impl Default for ContentServerConfig {
+    fn default() -> Self {
+        ContentServerConfig {
+            server_id: 0,
+            server_type: ServerType::Text,
+            file_directory: "files".to_string(),
+            media_directory: "media".to_string(),
+            debug: false,
+            options: ContentServerOptions::default(),
+        }
+    }
+}
+
+impl ContentServerConfig {
+    /// Parses a configuration written in TOML
+    /// # Errors
+    /// Returns `InvalidConfig` if the configuration can't be parsed
+    pub fn from_toml(raw: &str) -> Result<Self, ContentServerError> {
+        toml::from_str(raw).map_err(|err| ContentServerError::InvalidConfig(err.to_string()))
+    }
+
+    /// Parses a configuration written in JSON
+    /// # Errors
+    /// Returns `InvalidConfig` if the configuration can't be parsed
+    pub fn from_json(raw: &str) -> Result<Self, ContentServerError> {
+        serde_json::from_str(raw).map_err(|err| ContentServerError::InvalidConfig(err.to_string()))
+    }
+
+    /// Reads the configuration file, in TOML if its extension is `.toml` and in JSON otherwise
+    /// # Errors
+    /// Returns `InvalidConfig` if the file can't be read or parsed
+    pub fn load(path: &Path) -> Result<Self, ContentServerError> {
+        let raw = fs::read_to_string(path).map_err(|err| {
+            ContentServerError::InvalidConfig(format!(
+                "Error reading config '{}': {err}",
+                path.display()
+            ))
+        })?;
+        if path.extension().is_some_and(|ext| ext == "toml") {
+            Self::from_toml(&raw)
+        } else {
+            Self::from_json(&raw)
+        }
+    }
+}
+
+/// Writes a `Duration` as a number of milliseconds
+pub(crate) mod duration_ms {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(duration.as_millis() as u64)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        u64::deserialize(deserializer).map(Duration::from_millis)
+    }
+}
+
+/// Writes an optional `Duration` as a number of milliseconds, `None` is written as 0
+/// because TOML has no null, and both 0 and a JSON `null` are read as `None`
+pub(crate) mod optional_duration_ms {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    #[allow(clippy::cast_possible_truncation, clippy::ref_option)]
+    pub fn serialize<S: Serializer>(
+        duration: &Option<Duration>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(duration.map_or(0, |duration| duration.as_millis() as u64))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Duration>, D::Error> {
+        Option::<u64>::deserialize(deserializer).map(|millis| {
+            millis
+                .filter(|millis| *millis > 0)
+                .map(Duration::from_millis)
+        })
+    }
+}
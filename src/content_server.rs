@@ -1,10 +1,11 @@
-use chrono::Utc;
+use chrono::{DateTime, Utc};
+use encoding_rs::Encoding;
 use image::ImageFormat;
 use log::error;
-use rand::seq::SliceRandom;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use rustafarian_shared::assembler::{assembler::Assembler, disassembler::Disassembler};
 use rustafarian_shared::logger::LogLevel::{DEBUG, ERROR, INFO};
-use rustafarian_shared::logger::Logger;
 use rustafarian_shared::messages::browser_messages::{
     BrowserRequest, BrowserRequestWrapper, BrowserResponse, BrowserResponseWrapper,
 };
@@ -13,19 +14,42 @@ use rustafarian_shared::messages::commander_messages::{
 };
 use rustafarian_shared::messages::general_messages::{DroneSend, ServerType, ServerTypeResponse};
 use rustafarian_shared::topology::{compute_route_dijkstra, Topology};
-use std::collections::{HashMap, HashSet};
-use std::io::Cursor;
-use std::path::Path;
-use std::{env, fs, process};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::env;
+use std::io::{self, BufReader};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 use wg_2024::packet::{Ack, Nack, NackType, NodeType};
 use wg_2024::{
     network::{NodeId, SourceRoutingHeader},
-    packet::{FloodRequest, FloodResponse, Packet, PacketType},
+    packet::{FloodRequest, FloodResponse, Fragment, Packet, PacketType, FRAGMENT_DSIZE},
 };
 
 use rustafarian_shared::TIMEOUT_BETWEEN_FLOODS_MS;
 
-use crossbeam_channel::{select_biased, Receiver, Sender};
+use crate::compression::{self, ContentEncoding};
+use crate::config::{duration_ms, optional_duration_ms};
+use crate::content_store::{ContentMetadata, ContentStore, FileSystemStore};
+use crate::error::ContentServerError;
+use crate::history::ContentHistory;
+use crate::links;
+use crate::logging::{LogLevelFilter, ServerLogger};
+use crate::manifest::Manifest;
+use crate::media;
+use crate::messages::{
+    self, CatalogEntry, ChatRequest, ChatResponse, ContentChanges, ContentError, ContentEvent,
+    ContentKind, ContentRequest, ContentRequestWrapper, ContentResponse, ContentResponseWrapper,
+    FileDetails, MediaEncoding, TaggedFile, VersionedFile,
+};
+use crate::response_cache::{ResponseCache, ResponseKey};
+use crate::search::SearchIndex;
+use crate::selection::SelectionPolicy;
+use crate::stream::{Payload, ResponseStream};
+use crate::text;
+use crate::variants::{Variant, VariantKey};
+
+use crossbeam_channel::{never, select_biased, tick, Receiver, Sender};
+use serde::{Deserialize, Serialize};
 
 #[allow(dead_code)]
 pub struct ContentServer {
@@ -38,21 +62,154 @@ pub struct ContentServer {
     pub sent_packets: HashMap<u64, Vec<Packet>>,
     assembler: Assembler,
     deassembler: Disassembler,
-    pub files: HashMap<u8, String>,
-    media: HashMap<u8, String>,
+    pub files: Box<dyn ContentStore>,
+    pub media: Box<dyn ContentStore>,
     server_type: ServerType,
     pub packet_to_retry: HashSet<(u64, u64)>,
     flood_time: u128,
     is_debug: bool,
-    logger: Logger,
+    logger: ServerLogger,
+    rng: StdRng,
+    pub options: ContentServerOptions,
+    pub responses: ResponseCache,
+    file_directory: Option<PathBuf>,
+    media_directory: Option<PathBuf>,
+    /// Set by `ContentServerBuilder::content_listener`
+    pub(crate) content_listener: Option<Sender<ContentEvent>>,
+    uploads: Vec<u8>,
+    pub history: ContentHistory,
+    pub search_index: SearchIndex,
+    streams: HashMap<u64, OutgoingStream>,
+    /// Encoding of the response to each session whose request accepted a compressed one
+    encodings: HashMap<u64, ContentEncoding>,
+    /// Media encoding asked by the request of each session
+    media_encodings: HashMap<u64, MediaEncoding>,
+    /// Clients registered to a chat server
+    pub clients: BTreeSet<NodeId>,
+    /// Whether `run` keeps listening, it stops after a `Shutdown` command
+    is_running: bool,
+    /// Number of times each fragment has been sent again after a NACK, by session and index
+    retries: HashMap<(u64, u64), u32>,
+    /// Sessions carrying the error about a response that was given up,
+    /// no other error is sent if they are given up too
+    error_sessions: HashSet<u64>,
+}
+
+/// A response sent a few fragments at a time, the next ones are read when the previous ones are acknowledged
+struct OutgoingStream {
+    id: u8,
+    stream: ResponseStream,
+    route: Vec<u8>,
+    next_index: u64,
+    total_fragments: u64,
+}
+
+/// Options applied when a `ContentServer` loads its content,
+/// every option missing from a configuration file keeps its default
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ContentServerOptions {
+    /// Text files served by a text server, by default 10 random files
+    pub file_selection: SelectionPolicy,
+    /// Media files served by a media server, by default the first 30 files
+    pub media_selection: SelectionPolicy,
+    /// Seed of the random generator used to select the files and to create flood and session ids,
+    /// the same seed reproduces the same run, if missing the generator is seeded from entropy
+    pub seed: Option<u64>,
+    /// Send the stored media bytes untouched instead of decoding and encoding them again,
+    /// unless the client asks for a different format
+    pub media_passthrough: bool,
+    /// Maximum width and height of the thumbnails
+    pub thumbnail_dimension: u32,
+    /// Largest maximum dimension a client can ask for a media variant
+    pub max_variant_dimension: u32,
+    /// JPEG quality of the thumbnails, from 1 to 100
+    pub thumbnail_quality: u8,
+    /// Encoding of the text files that are not valid UTF-8 and have no byte order mark
+    pub text_fallback_encoding: String,
+    /// Maximum total size in bytes of the cached text and media responses, 0 disables the cache
+    pub response_cache_bytes: usize,
+    /// Time between two scans of the content directory looking for changed files,
+    /// `None` disables the rescans, written as `rescan_interval_ms = 0` in a configuration file
+    #[serde(rename = "rescan_interval_ms", with = "optional_duration_ms")]
+    pub rescan_interval: Option<Duration>,
+    /// Whether clients can publish new content with an `Upload` request, disabled by default
+    pub accept_uploads: bool,
+    /// Maximum size in bytes of an uploaded file
+    pub max_upload_bytes: usize,
+    /// Number of versions kept for each file, the oldest ones are dropped
+    pub max_versions: usize,
+    /// Directory where the versions that follow a change are archived, relative to the current
+    /// directory, if missing only the latest version of each file can be requested
+    pub history_directory: Option<PathBuf>,
+    /// Maximum number of files returned by a search
+    pub max_search_results: usize,
+    /// Files at least this big are streamed from their store instead of being loaded in memory
+    pub stream_threshold_bytes: u64,
+    /// Maximum number of fragments of a streamed file waiting for an ack
+    pub stream_window: usize,
+    /// How the bytes of the media files are sent to the clients that don't ask for an encoding
+    pub media_encoding: MediaEncoding,
+    /// Serve both the text files and the media files, the server type only decides
+    /// which ones are versioned, uploaded, described by the catalog and the metadata,
+    /// and listed in response to a `FileList` request
+    pub hybrid: bool,
+    /// Refuse a content directory holding a file without an id in its name that the manifest
+    /// doesn't list, when the server is created and on every rescan; by default it's logged and skipped
+    pub strict_file_names: bool,
+    /// Most detailed messages written to the log, when the server is in debug mode
+    pub log_level: LogLevelFilter,
+    /// Minimum time between two flood requests sent by the server
+    #[serde(rename = "flood_interval_ms", with = "duration_ms")]
+    pub flood_interval: Duration,
+    /// Number of times a fragment is sent again after a NACK before the server gives up on it
+    pub max_fragment_retries: u32,
+}
+
+/// Media ready to be sent to a client
+struct EncodedMedia {
+    data: Vec<u8>,
+    format: ImageFormat,
+    /// The bytes are the stored ones, not encoded again
+    original: bool,
 }
 
+impl Default for ContentServerOptions {
+    fn default() -> Self {
+        ContentServerOptions {
+            file_selection: SelectionPolicy::Random(10),
+            media_selection: SelectionPolicy::FirstSorted(30),
+            seed: None,
+            media_passthrough: false,
+            thumbnail_dimension: 128,
+            max_variant_dimension: 1024,
+            thumbnail_quality: 70,
+            text_fallback_encoding: "windows-1252".to_string(),
+            response_cache_bytes: 16 * 1024 * 1024,
+            rescan_interval: Some(Duration::from_secs(5)),
+            accept_uploads: false,
+            max_upload_bytes: 1024 * 1024,
+            max_versions: 10,
+            history_directory: None,
+            max_search_results: 10,
+            stream_threshold_bytes: 1024 * 1024,
+            stream_window: 32,
+            media_encoding: MediaEncoding::Array,
+            hybrid: false,
+            strict_file_names: false,
+            log_level: LogLevelFilter::Debug,
+            flood_interval: Duration::from_millis(TIMEOUT_BETWEEN_FLOODS_MS),
+            max_fragment_retries: 10,
+        }
+    }
+}
 
 impl ContentServer {
     /// Returns a instance of `ContentServer`
+    /// # Errors
+    /// Returns an error if the content directories of the server type can't be loaded
     #[allow(dead_code)]
     #[allow(clippy::too_many_arguments)]
-    #[allow(clippy::too_many_lines)]
     pub fn new(
         server_id: u8,
         senders: HashMap<u8, Sender<Packet>>,
@@ -63,113 +220,473 @@ impl ContentServer {
         media_directory: &str,
         server_type: ServerType,
         is_debug: bool,
-    ) -> Self {
+    ) -> Result<Self, ContentServerError> {
+        Self::with_options(
+            server_id,
+            senders,
+            receiver,
+            sim_controller_receiver,
+            sim_controller_sender,
+            file_directory,
+            media_directory,
+            server_type,
+            is_debug,
+            ContentServerOptions::default(),
+        )
+    }
+
+    /// Returns a instance of `ContentServer` configured with the given options
+    /// # Errors
+    /// Returns an error if the content directories of the server type can't be loaded,
+    /// or if a chat server is asked to be hybrid
+    #[allow(clippy::too_many_arguments)]
+    pub fn with_options(
+        server_id: u8,
+        senders: HashMap<u8, Sender<Packet>>,
+        receiver: Receiver<Packet>,
+        sim_controller_receiver: Receiver<SimControllerCommand>,
+        sim_controller_sender: Sender<SimControllerResponseWrapper>,
+        file_directory: &str,
+        media_directory: &str,
+        server_type: ServerType,
+        is_debug: bool,
+        options: ContentServerOptions,
+    ) -> Result<Self, ContentServerError> {
         // Retrieves current directory
-        let Ok(current_dir) = env::current_dir() else {
-            eprintln!("Error: Failed to get current directory.");
-            std::process::exit(1);
+        let current_dir = env::current_dir().map_err(ContentServerError::CurrentDirectory)?;
+        let is_chat = matches!(server_type, ServerType::Chat);
+        if is_chat && options.hybrid {
+            return Err(ContentServerError::UnsupportedType(
+                "ServerType::Chat has no content and can't be hybrid".to_string(),
+            ));
+        }
+        options.file_selection.validate()?;
+        options.media_selection.validate()?;
+
+        let mut rng = options
+            .seed
+            .map_or_else(StdRng::from_entropy, StdRng::seed_from_u64);
+
+        let file_directory = current_dir.join(file_directory);
+        let media_directory = current_dir.join(media_directory);
+        // Load the files or the media based on the server type, or both for a hybrid server,
+        // a chat server has no content
+        let files = if options.hybrid || matches!(server_type, ServerType::Text) {
+            Self::check_directory(&file_directory)?;
+            let (files, invalid) = Self::load_directory(
+                &file_directory,
+                &ServerType::Text,
+                &options,
+                None,
+                |found| options.file_selection.apply(found, &mut rng),
+            );
+            Self::check_content(&file_directory, &files, invalid, options.strict_file_names)?;
+            files
+        } else {
+            FileSystemStore::default()
+        };
+        let media = if options.hybrid || matches!(server_type, ServerType::Media) {
+            Self::check_directory(&media_directory)?;
+            let (media, invalid) = Self::load_directory(
+                &media_directory,
+                &ServerType::Media,
+                &options,
+                None,
+                |found| options.media_selection.apply(found, &mut rng),
+            );
+            Self::check_content(&media_directory, &media, invalid, options.strict_file_names)?;
+            media
+        } else {
+            FileSystemStore::default()
+        };
+
+        let mut server = Self::build(
+            server_id,
+            senders,
+            receiver,
+            sim_controller_receiver,
+            sim_controller_sender,
+            Box::new(files),
+            Box::new(media),
+            server_type,
+            is_debug,
+        );
+        server.rng = rng;
+        server.responses = ResponseCache::new(options.response_cache_bytes);
+        server.logger.level = options.log_level;
+        server.options = options;
+        server.history = match &server.options.history_directory {
+            Some(directory) => ContentHistory::in_directory(
+                &current_dir.join(directory),
+                server.options.max_versions,
+            ),
+            None => ContentHistory::in_memory(server.options.max_versions),
         };
+        server.record_versions(None);
+        server.index_text(None);
+        server.file_directory = Some(file_directory);
+        server.media_directory = Some(media_directory);
+        Ok(server)
+    }
 
-        // Initialize hashmaps for files and media
-        let mut files = HashMap::new();
-        let mut media = HashMap::new();
+    /// Checks that the content directory exists
+    fn check_directory(directory: &Path) -> Result<(), ContentServerError> {
+        if directory.exists() {
+            Ok(())
+        } else {
+            Err(ContentServerError::DirectoryMissing(
+                directory.to_path_buf(),
+            ))
+        }
+    }
 
-        // Configure based on the server type
-        match server_type {
-            // If it's a text server upload text files
-            ServerType::Text => {
-                let file_path = current_dir.join(file_directory);
-                // Check if the directory exist
-                if !file_path.exists() {
-                    error!(
-                        "Error: File directory '{}' does not exist!\n",
-                        file_path.display()
-                    );
-                    std::process::exit(1);
-                }
-                let mut file_list = Vec::new();
-                // Reads files from directory and places them in files hashmap
-                if let Ok(entries) = fs::read_dir(file_path) {
-                    for entry in entries.filter_map(Result::ok) {
-                        if let Some(path) = entry.path().to_str() {
-                            if let Some(filename) = entry.file_name().to_str() {
-                                // Check extension
-                                if std::path::Path::new(filename)
-                                    .extension()
-                                    .map_or(false, |ext| ext.eq_ignore_ascii_case("txt"))
-                                {
-                                    // Parse the numeric character
-                                    if let Ok(id) = filename.trim_end_matches(".txt").parse::<u8>()
-                                    {
-                                        file_list.push((id, path.to_string()));
-                                    } else {
-                                        error!(
-                                            "Warning: Failed to parse ID from filename '{}'\n",
-                                            filename
-                                        );
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-                // Select only 10 random
-                let mut rng = rand::thread_rng();
-                file_list.shuffle(&mut rng);
-                let selected_files = file_list.into_iter().take(10);
-                for (id, path) in selected_files {
-                    files.insert(id, path);
-                }
+    /// Checks the content loaded from a directory, when the server is created and on every rescan:
+    /// there must be something to serve, and with `strict_file_names` every file must have an id,
+    /// otherwise the files without one are only logged
+    fn check_content(
+        directory: &Path,
+        store: &FileSystemStore,
+        invalid: Vec<PathBuf>,
+        strict: bool,
+    ) -> Result<(), ContentServerError> {
+        for path in invalid {
+            if strict {
+                return Err(ContentServerError::InvalidFileName(path));
             }
-            // If it's a media server upload media files
-            ServerType::Media => {
-                let media_path = current_dir.join(media_directory);
-                // Check if the directory exist
-                if !media_path.exists() {
-                    error!(
-                        "Error: Media directory '{}' does not exist!\n",
-                        media_path.display()
-                    );
-                    std::process::exit(1);
-                }
-                // Reads files from directory and places them in media hashmap
-                let mut media_list = Vec::new();
-                if let Ok(entries) = fs::read_dir(media_path) {
-                    for entry in entries.filter_map(Result::ok) {
-                        if let Some(path) = entry.path().to_str() {
-                            if let Some(file_name) = entry.file_name().to_str() {
-                                if Path::new(file_name)
-                                    .extension()
-                                    .map_or(false, |ext| ext.eq_ignore_ascii_case("jpg"))
-                                {
-                                    // Parse name
-                                    if let Ok(id) = file_name.trim_end_matches(".jpg").parse::<u8>()
-                                    {
-                                        media_list.push((id, path.to_string()));
-                                    } else {
-                                        error!(
-                                            "Unable to parse ID from file name '{}'\n",
-                                            file_name
-                                        );
-                                    }
-                                }
-                            }
-                        }
+            error!(
+                "Warning: Failed to parse ID from filename '{}'\n",
+                path.display()
+            );
+        }
+        if store.list().is_empty() {
+            return Err(ContentServerError::NoContent(directory.to_path_buf()));
+        }
+        Ok(())
+    }
+
+    /// Reads the files of the server type from the directory, plus the ones listed in its manifest,
+    /// and keeps the ones selected by `select`; the encoding and the links of text files are detected.
+    /// The files unchanged since they were loaded in `previous` are not read again.
+    /// Also returns the files whose name is not an id and that the manifest doesn't list
+    fn load_directory(
+        directory: &Path,
+        server_type: &ServerType,
+        options: &ContentServerOptions,
+        previous: Option<&dyn ContentStore>,
+        select: impl FnOnce(Vec<(u8, PathBuf)>) -> Vec<(u8, PathBuf)>,
+    ) -> (FileSystemStore, Vec<PathBuf>) {
+        let extensions = match server_type {
+            ServerType::Text => vec!["txt"],
+            _ => media::readable_extensions(),
+        };
+        let (mut found, mut invalid) = FileSystemStore::scan_names(directory, &extensions);
+        let manifest = Manifest::load(directory);
+        // Files with an explicit path in the manifest replace the ones named after their id
+        if let Some(manifest) = &manifest {
+            for (id, path) in manifest.sources(directory) {
+                found.retain(|(found_id, _)| *found_id != id);
+                invalid.retain(|invalid_path| *invalid_path != path);
+                found.push((id, path));
+            }
+        }
+        let mut store = FileSystemStore::rescan(select(found).into_iter().collect(), previous)
+            .with_directory(directory);
+        if let Some(manifest) = &manifest {
+            store.apply_manifest(manifest);
+        }
+        if matches!(server_type, ServerType::Text) {
+            store.detect_encodings(text::encoding_for_label(&options.text_fallback_encoding));
+            store.index_media_references();
+        }
+        (store, invalid)
+    }
+
+    /// Scans the content directories again and replaces the served content with what it finds,
+    /// the cached responses of the changed files are dropped and the controller is notified.
+    /// A directory that would fail `check_content` keeps its current content.
+    /// Returns the changes, or `None` if the server has no content directory it could scan
+    pub fn reload_content(&mut self) -> Option<ContentChanges> {
+        let mut changes: Option<ContentChanges> = None;
+        for kind in [ContentKind::Text, ContentKind::Media] {
+            if !self.serves(kind) {
+                continue;
+            }
+            if let Some(kind_changes) = self.reload_kind(kind) {
+                let changes = changes.get_or_insert_with(ContentChanges::default);
+                changes.added.extend(kind_changes.added);
+                changes.modified.extend(kind_changes.modified);
+                changes.removed.extend(kind_changes.removed);
+            }
+        }
+        changes
+    }
+
+    /// Scans the content directory of a kind of content again, see `reload_content`
+    fn reload_kind(&mut self, kind: ContentKind) -> Option<ContentChanges> {
+        let (directory, selection, server_type) = match kind {
+            ContentKind::Text => (
+                self.file_directory.clone()?,
+                &self.options.file_selection,
+                ServerType::Text,
+            ),
+            ContentKind::Media => (
+                self.media_directory.clone()?,
+                &self.options.media_selection,
+                ServerType::Media,
+            ),
+        };
+        // Keep serving the current content if the directory disappeared
+        if !directory.exists() {
+            self.logger.log(
+                format!(
+                    "Error: Content directory '{}' does not exist!\n",
+                    directory.display()
+                )
+                .as_str(),
+                ERROR,
+            );
+            return None;
+        }
+        let current = match kind {
+            ContentKind::Text => &self.files,
+            ContentKind::Media => &self.media,
+        };
+        let current_ids = current.list();
+        // Only the content of the server type is uploaded
+        let uploads = if self.primary_kind() == Some(kind) {
+            self.uploads.as_slice()
+        } else {
+            &[]
+        };
+        let rng = &mut self.rng;
+        let (store, invalid) = Self::load_directory(
+            &directory,
+            &server_type,
+            &self.options,
+            Some(current.as_ref()),
+            |found| {
+                // Uploaded files are always served, whatever the selection policy
+                let uploaded: Vec<(u8, PathBuf)> = found
+                    .iter()
+                    .filter(|(id, _)| uploads.contains(id))
+                    .cloned()
+                    .collect();
+                let mut selected = selection.reapply(found, &current_ids, rng);
+                for (id, path) in uploaded {
+                    if !selected.iter().any(|(selected_id, _)| *selected_id == id) {
+                        selected.push((id, path));
                     }
                 }
-                // Select only 30
+                selected
+            },
+        );
+        // A directory the server could not be created with is not served either
+        if let Err(err) =
+            Self::check_content(&directory, &store, invalid, self.options.strict_file_names)
+        {
+            self.logger.log(
+                format!("Error: {err}, keeping the current content\n").as_str(),
+                ERROR,
+            );
+            return None;
+        }
 
-                let selected_media = media_list.into_iter().take(30);
-                for (id, path) in selected_media {
-                    media.insert(id, path);
+        // Compare the digests of the old and new content
+        let mut changes = ContentChanges::default();
+        for id in store.list() {
+            match current.metadata(id) {
+                None => changes.added.push(id),
+                Some(old) if old.sha256 != store.metadata(id).and_then(|m| m.sha256) => {
+                    changes.modified.push(id);
                 }
+                Some(_) => {}
             }
-            // If it's a chat server gives error
-            ServerType::Chat => {
-                error!("Error: ServerType::Chat is not supported!\n");
-                std::process::exit(1);
+        }
+        changes.removed = current_ids
+            .into_iter()
+            .filter(|id| !store.contains(*id))
+            .collect();
+
+        // Swap the whole store at once, so requests never see a partial scan
+        match kind {
+            ContentKind::Text => self.files = Box::new(store),
+            ContentKind::Media => self.media = Box::new(store),
+        }
+        if !changes.is_empty() {
+            let changed: Vec<u8> = changes
+                .added
+                .iter()
+                .chain(&changes.modified)
+                .copied()
+                .collect();
+            if self.primary_kind() == Some(kind) {
+                self.record_versions(Some(&changed));
+            }
+            self.notify_content_changes(kind, &changes);
+        }
+        Some(changes)
+    }
+
+    /// Checks if the server serves that kind of content
+    pub fn serves(&self, kind: ContentKind) -> bool {
+        match self.primary_kind() {
+            Some(primary) => self.options.hybrid || primary == kind,
+            None => false,
+        }
+    }
+
+    /// Returns the kinds of content served, none for a chat server
+    fn served_kinds(&self) -> Vec<ContentKind> {
+        [ContentKind::Text, ContentKind::Media]
+            .into_iter()
+            .filter(|&kind| self.serves(kind))
+            .collect()
+    }
+
+    /// Returns the kind of content of the server type
+    fn primary_kind(&self) -> Option<ContentKind> {
+        match self.server_type {
+            ServerType::Text => Some(ContentKind::Text),
+            ServerType::Media => Some(ContentKind::Media),
+            ServerType::Chat => None,
+        }
+    }
+
+    /// Returns the store of a kind of content
+    fn store(&self, kind: ContentKind) -> &dyn ContentStore {
+        match kind {
+            ContentKind::Text => self.files.as_ref(),
+            ContentKind::Media => self.media.as_ref(),
+        }
+    }
+
+    /// Returns the kind of content asked by a request, the one of the server type if the request
+    /// doesn't say, or sends a `WrongServerType` error and returns `None` if it's not served
+    fn requested_kind(
+        &mut self,
+        kind: Option<ContentKind>,
+        source_id: NodeId,
+        session_id: u64,
+        route: &[u8],
+    ) -> Option<ContentKind> {
+        let message = match kind.or_else(|| self.primary_kind()) {
+            Some(kind) if self.serves(kind) => return Some(kind),
+            Some(kind) => format!("This server does not serve {kind:?} files"),
+            None => "ServerType::Chat has no files".to_string(),
+        };
+        self.send_error(
+            ContentError::WrongServerType(message),
+            source_id,
+            session_id,
+            route,
+        );
+        None
+    }
+
+    /// Checks that a request about versions or uploads asks for the kind of the server type,
+    /// the only one with a history and uploads, otherwise sends a `WrongServerType` error
+    fn check_primary_kind(
+        &mut self,
+        kind: Option<ContentKind>,
+        source_id: NodeId,
+        session_id: u64,
+        route: &[u8],
+    ) -> bool {
+        let Some(kind) = self.requested_kind(kind, source_id, session_id, route) else {
+            return false;
+        };
+        if self.primary_kind() == Some(kind) {
+            return true;
+        }
+        let message = format!("This server keeps no versions or uploads of {kind:?} files");
+        self.send_error(
+            ContentError::WrongServerType(message),
+            source_id,
+            session_id,
+            route,
+        );
+        false
+    }
+
+    /// Drops the cached responses of the changed content and tells the controller what changed
+    fn notify_content_changes(&mut self, kind: ContentKind, changes: &ContentChanges) {
+        self.logger.log(
+            format!(
+                "Server {} content changed: added {:?}, modified {:?}, removed {:?}\n",
+                self.server_id, changes.added, changes.modified, changes.removed
+            )
+            .as_str(),
+            INFO,
+        );
+        for id in changes.modified.iter().chain(&changes.removed) {
+            self.invalidate_content(*id);
+        }
+        if kind == ContentKind::Text {
+            for id in &changes.removed {
+                self.search_index.remove(*id);
+            }
+            let changed: Vec<u8> = changes
+                .added
+                .iter()
+                .chain(&changes.modified)
+                .copied()
+                .collect();
+            self.index_text(Some(&changed));
+        }
+        if let Some(listener) = &self.content_listener {
+            let event = ContentEvent::ContentChanged {
+                server_id: self.server_id,
+                changes: changes.clone(),
+            };
+            if listener.send(event).is_err() {
+                self.logger
+                    .log("Error: Content listener disconnected\n", ERROR);
             }
         }
+    }
+
+    /// Returns a instance of `ContentServer` serving the content of the given stores
+    #[allow(clippy::too_many_arguments)]
+    pub fn with_stores(
+        server_id: u8,
+        senders: HashMap<u8, Sender<Packet>>,
+        receiver: Receiver<Packet>,
+        sim_controller_receiver: Receiver<SimControllerCommand>,
+        sim_controller_sender: Sender<SimControllerResponseWrapper>,
+        files: Box<dyn ContentStore>,
+        media: Box<dyn ContentStore>,
+        server_type: ServerType,
+        is_debug: bool,
+    ) -> Self {
+        let mut server = Self::build(
+            server_id,
+            senders,
+            receiver,
+            sim_controller_receiver,
+            sim_controller_sender,
+            files,
+            media,
+            server_type,
+            is_debug,
+        );
+        server.record_versions(None);
+        server.index_text(None);
+        server
+    }
+
+    /// Creates the instance of `ContentServer`, without recording the versions of its content
+    #[allow(clippy::too_many_arguments)]
+    fn build(
+        server_id: u8,
+        senders: HashMap<u8, Sender<Packet>>,
+        receiver: Receiver<Packet>,
+        sim_controller_receiver: Receiver<SimControllerCommand>,
+        sim_controller_sender: Sender<SimControllerResponseWrapper>,
+        files: Box<dyn ContentStore>,
+        media: Box<dyn ContentStore>,
+        server_type: ServerType,
+        is_debug: bool,
+    ) -> Self {
         // Create and return a new instance of ContentServer
         ContentServer {
             server_id,
@@ -186,12 +703,29 @@ impl ContentServer {
             server_type,
             flood_time: 0,
             is_debug,
-            logger: Logger::new("Content Server".to_string(), server_id, is_debug),
+            logger: ServerLogger::new("Content Server".to_string(), server_id, is_debug),
             packet_to_retry: HashSet::new(),
+            rng: StdRng::from_entropy(),
+            options: ContentServerOptions::default(),
+            responses: ResponseCache::new(ContentServerOptions::default().response_cache_bytes),
+            file_directory: None,
+            media_directory: None,
+            content_listener: None,
+            uploads: Vec::new(),
+            history: ContentHistory::in_memory(ContentServerOptions::default().max_versions),
+            search_index: SearchIndex::default(),
+            streams: HashMap::new(),
+            encodings: HashMap::new(),
+            media_encodings: HashMap::new(),
+            clients: BTreeSet::new(),
+            is_running: false,
+            retries: HashMap::new(),
+            error_sessions: HashSet::new(),
         }
     }
 
-    /// Keeps the server active and continuously listens to two main channels
+    /// Keeps the server active and continuously listens to two main channels,
+    /// returns when the controller sends `Shutdown`
     #[allow(dead_code)]
     pub fn run(&mut self) {
         self.logger.log(
@@ -200,7 +734,10 @@ impl ContentServer {
         );
         // Send a flood request to obtain the initial topology
 
-        loop {
+        // Ticks when the content directory has to be scanned again
+        let rescan = self.options.rescan_interval.map_or_else(never, tick);
+        self.is_running = true;
+        while self.is_running {
             select_biased! {
                 // Receives a command from the simulator
                 recv(self.sim_controller_receiver) -> packet => {
@@ -210,6 +747,10 @@ impl ContentServer {
                 recv(self.receiver) -> packet => {
                     self.handle_drone_packets(packet);
                 }
+                // Looks for added, modified and removed content
+                recv(rescan) -> _ => {
+                    self.reload_content();
+                }
             }
         }
     }
@@ -246,7 +787,7 @@ impl ContentServer {
                     SimControllerCommand::Shutdown => {
                         self.logger.log("Received Shutdown from SC", DEBUG);
                         self.logger.log("Shutting down", INFO);
-                        process::exit(0);
+                        self.is_running = false;
                     }
                     // Other commands are not for this type of server
                     _ => {
@@ -395,34 +936,38 @@ impl ContentServer {
                             }
                             // Request asks for a text file content
                             BrowserRequest::TextFileRequest(id) => {
-                                // Check if it's a text server and process the request
-                                match self.server_type {
-                                    ServerType::Text => {
-                                        self.handle_file_request(id, source_id, session_id, route);
-                                    }
-                                    // If it's a media server print error
-                                    _ => {
-                                        self.logger.log(
-                                            "This server cannot handle text file requests\n",
-                                            ERROR,
-                                        );
-                                    }
+                                // Check if the server serves text files and process the request
+                                if self.serves(ContentKind::Text) {
+                                    self.handle_file_request(id, source_id, session_id, route);
+                                } else {
+                                    // If it's a media server send an error
+                                    self.send_error(
+                                        ContentError::WrongServerType(
+                                            "This server cannot handle text file requests"
+                                                .to_string(),
+                                        ),
+                                        source_id,
+                                        session_id,
+                                        route,
+                                    );
                                 }
                             }
                             // Request asks for a media file content
                             BrowserRequest::MediaFileRequest(id) => {
-                                // Check if it's a media server and process the request
-                                match self.server_type {
-                                    ServerType::Media => {
-                                        self.handle_media_request(id, source_id, session_id, route);
-                                    }
-                                    // If it's a text server print error
-                                    _ => {
-                                        self.logger.log(
-                                            "This server cannot handle media file requests\n",
-                                            ERROR,
-                                        );
-                                    }
+                                // Check if the server serves media files and process the request
+                                if self.serves(ContentKind::Media) {
+                                    self.handle_media_request(id, source_id, session_id, route);
+                                } else {
+                                    // If it's a text server send an error
+                                    self.send_error(
+                                        ContentError::WrongServerType(
+                                            "This server cannot handle media file requests"
+                                                .to_string(),
+                                        ),
+                                        source_id,
+                                        session_id,
+                                        route,
+                                    );
                                 }
                             }
                         }
@@ -433,152 +978,1337 @@ impl ContentServer {
                     }
                 }
             }
-            // If's there is an error print it
-            Err(err) => {
+            // If it's not a browser request it can be a content server request
+            Err(err) => match ContentRequestWrapper::from_string(raw_content) {
+                Ok(ContentRequestWrapper::Content(request)) => {
+                    self.process_content_request(source_id, session_id, request, route);
+                }
+                // Check if it's a chat server and process the chat request
+                Ok(ContentRequestWrapper::Chat(request)) => match self.server_type {
+                    ServerType::Chat => {
+                        self.process_chat_request(source_id, session_id, request, route);
+                    }
+                    _ => {
+                        self.send_error(
+                            ContentError::WrongServerType(
+                                "This server cannot handle chat requests".to_string(),
+                            ),
+                            source_id,
+                            session_id,
+                            route,
+                        );
+                    }
+                },
+                // The client accepts a compressed response to the wrapped request
+                Ok(ContentRequestWrapper::AcceptEncoding { encodings, request }) => {
+                    if let Some(encoding) = encodings.first() {
+                        self.encodings.insert(session_id, *encoding);
+                    }
+                    self.process_request(source_id, session_id, &request, route);
+                    // The request may have been answered without a message
+                    self.encodings.remove(&session_id);
+                }
+                // The client asks for the media encoding of the response to the wrapped request
+                Ok(ContentRequestWrapper::AcceptMediaEncoding { encoding, request }) => {
+                    self.media_encodings.insert(session_id, encoding);
+                    self.process_request(source_id, session_id, &request, route);
+                    self.media_encodings.remove(&session_id);
+                }
+                // If's there is an error send it back
+                Err(_) => {
+                    self.logger.log(
+                        format!("Raw content of the bad request is {raw_content}\n").as_str(),
+                        DEBUG,
+                    );
+                    self.send_error(
+                        ContentError::BadRequest(format!("Error deserializing request: {err}")),
+                        source_id,
+                        session_id,
+                        route,
+                    );
+                }
+            },
+        }
+    }
+
+    /// Handles the requests specific to the content server
+    fn process_content_request(
+        &mut self,
+        source_id: NodeId,
+        session_id: u64,
+        request: ContentRequest,
+        route: &[u8],
+    ) {
+        match request {
+            // Request asks for the files list with the catalog details
+            ContentRequest::FileCatalog { kind } => {
+                if let Some(kind) = self.requested_kind(kind, source_id, session_id, route) {
+                    self.handle_file_catalog(kind, source_id, session_id, route);
+                }
+            }
+            // Request asks for a media file, optionally in a given format
+            ContentRequest::MediaFile { id, format } => {
+                if self.serves(ContentKind::Media) {
+                    self.handle_negotiated_media_request(
+                        id,
+                        format.as_deref(),
+                        source_id,
+                        session_id,
+                        route,
+                    );
+                } else {
+                    // If it's a text server send an error
+                    self.send_error(
+                        ContentError::WrongServerType(
+                            "This server cannot handle media file requests".to_string(),
+                        ),
+                        source_id,
+                        session_id,
+                        route,
+                    );
+                }
+            }
+            // Request asks for a thumbnail or a resized rendition of a media file
+            ContentRequest::Thumbnail(id) => {
+                let key = VariantKey {
+                    id,
+                    max_dimension: self.options.thumbnail_dimension,
+                    quality: self.options.thumbnail_quality,
+                };
+                self.handle_variant_request(key, source_id, session_id, route);
+            }
+            ContentRequest::MediaVariant {
+                id,
+                max_dimension,
+                quality,
+            } => {
+                let key = VariantKey::quantized(
+                    id,
+                    max_dimension,
+                    quality,
+                    self.options.max_variant_dimension,
+                );
+                self.handle_variant_request(key, source_id, session_id, route);
+            }
+            // Request asks for the files list with the current versions
+            ContentRequest::VersionedFileList { kind } => {
+                if self.check_primary_kind(kind, source_id, session_id, route) {
+                    self.handle_versioned_files_list(source_id, session_id, route);
+                }
+            }
+            // Request asks for a version of a file
+            ContentRequest::FileVersion { id, version, kind } => {
+                if self.check_primary_kind(kind, source_id, session_id, route) {
+                    self.handle_version_request(id, version, source_id, session_id, route);
+                }
+            }
+            // Request searches the text files
+            ContentRequest::Search { query } => {
+                if self.serves(ContentKind::Text) {
+                    self.handle_search_request(&query, source_id, session_id, route);
+                } else {
+                    // If it's a media server send an error
+                    self.send_error(
+                        ContentError::WrongServerType(
+                            "This server cannot handle search requests".to_string(),
+                        ),
+                        source_id,
+                        session_id,
+                        route,
+                    );
+                }
+            }
+            // Request asks for the details of a file
+            ContentRequest::Metadata { id, kind } => {
+                if let Some(kind) = self.requested_kind(kind, source_id, session_id, route) {
+                    self.handle_metadata_request(id, kind, source_id, session_id, route);
+                }
+            }
+            // Request asks for the files list with the details of each file
+            ContentRequest::DetailedFileList { kind } => {
+                if let Some(kind) = self.requested_kind(kind, source_id, session_id, route) {
+                    self.handle_detailed_files_list(kind, source_id, session_id, route);
+                }
+            }
+            // Request asks for a slice of a file
+            ContentRequest::Range {
+                id,
+                offset,
+                length,
+                kind,
+            } => {
+                if let Some(kind) = self.requested_kind(kind, source_id, session_id, route) {
+                    self.handle_range_request(
+                        id, kind, offset, length, source_id, session_id, route,
+                    );
+                }
+            }
+            // Request publishes a new file
+            ContentRequest::Upload { data, kind } => {
+                if self.check_primary_kind(kind, source_id, session_id, route) {
+                    self.handle_upload_request(data, source_id, session_id, route);
+                }
+            }
+            // Request asks for the media files referenced by a text file
+            ContentRequest::MediaReferences(id) => {
+                if self.serves(ContentKind::Text) {
+                    self.handle_media_references_request(id, source_id, session_id, route);
+                } else {
+                    // If it's a media server send an error
+                    self.send_error(
+                        ContentError::WrongServerType(
+                            "This server cannot handle media references requests".to_string(),
+                        ),
+                        source_id,
+                        session_id,
+                        route,
+                    );
+                }
+            }
+            // Request asks for the kinds of content served
+            ContentRequest::Capabilities => {
+                self.handle_capabilities_request(source_id, session_id, route);
+            }
+            // Request asks for the files of every kind served
+            ContentRequest::TaggedFileList => {
+                self.handle_tagged_files_list(source_id, session_id, route);
+            }
+        }
+    }
+
+    /// Handles the requests of the clients of a chat server
+    fn process_chat_request(
+        &mut self,
+        source_id: NodeId,
+        session_id: u64,
+        request: ChatRequest,
+        route: &[u8],
+    ) {
+        match request {
+            // Request registers the client
+            ChatRequest::Register => {
+                self.handle_register_request(source_id, session_id, route);
+            }
+            // Request asks for the registered clients
+            ChatRequest::ClientList => {
+                self.handle_client_list(source_id, session_id, route);
+            }
+            // Request sends a message to another client
+            ChatRequest::SendMessage { to, message } => {
+                self.handle_chat_message(to, &message, source_id, session_id, route);
+            }
+        }
+    }
+
+    /// Registers the client and confirms it with a `Registered` message
+    pub fn handle_register_request(&mut self, source_id: NodeId, session_id: u64, route: &[u8]) {
+        self.logger.log(
+            format!(
+                "Client {} registered to server {}\n",
+                source_id, self.server_id
+            )
+            .as_str(),
+            INFO,
+        );
+        self.clients.insert(source_id);
+
+        // Create a response confirming the registration
+        let response = ContentResponseWrapper::Chat(ChatResponse::Registered);
+        // Serialize the response
+        let response_json = response.stringify();
+        // Send message to client
+        self.send_message(source_id, &response_json, session_id, route);
+    }
+
+    /// Send the ids of the registered clients with a `ClientList` message
+    pub fn handle_client_list(&mut self, source_id: NodeId, session_id: u64, route: &[u8]) {
+        self.logger.log(
+            format!(
+                "Client {} requested client list from server {}\n",
+                source_id, self.server_id
+            )
+            .as_str(),
+            INFO,
+        );
+        // Create a response with the client ids
+        let response = ContentResponseWrapper::Chat(ChatResponse::ClientList(
+            self.clients.iter().copied().collect(),
+        ));
+        // Serialize the response
+        let response_json = response.stringify();
+        // Send message to client
+        self.send_message(source_id, &response_json, session_id, route);
+    }
+
+    /// Relays a message to another registered client with a `MessageFrom` message,
+    /// along the shortest route known, and confirms it to the sender with a `MessageSent` message
+    pub fn handle_chat_message(
+        &mut self,
+        to: NodeId,
+        message: &str,
+        source_id: NodeId,
+        session_id: u64,
+        route: &[u8],
+    ) {
+        self.logger.log(
+            format!(
+                "Client {} sent a message to client {} through server {}\n",
+                source_id, to, self.server_id
+            )
+            .as_str(),
+            INFO,
+        );
+        // Both clients must be registered
+        for client in [source_id, to] {
+            if !self.clients.contains(&client) {
+                self.send_error(
+                    ContentError::BadRequest(format!("Client {client} is not registered")),
+                    source_id,
+                    session_id,
+                    route,
+                );
+                return;
+            }
+        }
+        let route_to_client = compute_route_dijkstra(&mut self.topology, self.server_id, to);
+        // If there is no known route discover the network again
+        if route_to_client.is_empty() {
+            self.send_flood_request();
+            self.send_error(
+                ContentError::InternalError(format!("No route to client {to}")),
+                source_id,
+                session_id,
+                route,
+            );
+            return;
+        }
+
+        // Create the message for the other client, in a new session
+        let relayed = ContentResponseWrapper::Chat(ChatResponse::MessageFrom {
+            from: source_id,
+            message: message.to_string(),
+        });
+        let relayed_json = relayed.stringify();
+        // Messages are sent along the reverse of a route, so give the route from the client
+        let relay_route: Vec<u8> = route_to_client.into_iter().rev().collect();
+        let relay_session_id = self.rng.gen();
+        self.send_message(to, &relayed_json, relay_session_id, &relay_route);
+
+        // Create a response confirming the message was relayed
+        let response = ContentResponseWrapper::Chat(ChatResponse::MessageSent { to });
+        // Serialize the response
+        let response_json = response.stringify();
+        // Send message to client
+        self.send_message(source_id, &response_json, session_id, route);
+    }
+
+    /// Send a list of the server file IDs with a `FileList` message matching the server type
+    pub fn handle_files_list(&mut self, source_id: NodeId, session_id: u64, route: &[u8]) {
+        self.logger.log(
+            format!(
+                "Client {} requested file list from server {} of type {:?}\n",
+                source_id, self.server_id, self.server_type
+            )
+            .as_str(),
+            INFO,
+        );
+        //Take file IDs from hashmap
+        let file_ids = match self.server_type {
+            ServerType::Text => self.files.list(),
+            ServerType::Media => self.media.list(),
+            ServerType::Chat => {
+                self.send_error(
+                    ContentError::WrongServerType("ServerType::Chat has no files".to_string()),
+                    source_id,
+                    session_id,
+                    route,
+                );
+                return;
+            }
+        };
+
+        // Create a response with file IDs
+        let request = BrowserResponseWrapper::Chat(BrowserResponse::FileList(file_ids));
+        // Serialize the response
+        let request_json = request.stringify();
+        // Send message to client
+        self.send_message(source_id, &request_json, session_id, route);
+    }
+
+    /// Send the ids of the files of every kind served with a `TaggedFileList` message
+    fn handle_tagged_files_list(&mut self, source_id: NodeId, session_id: u64, route: &[u8]) {
+        self.logger.log(
+            format!(
+                "Client {} requested tagged file list from server {}\n",
+                source_id, self.server_id
+            )
+            .as_str(),
+            INFO,
+        );
+        let files = self
+            .served_kinds()
+            .into_iter()
+            .flat_map(|kind| {
+                self.store(kind)
+                    .list()
+                    .into_iter()
+                    .map(move |id| TaggedFile { kind, id })
+            })
+            .collect();
+
+        // Create a response with the tagged file IDs
+        let response = ContentResponseWrapper::Content(ContentResponse::TaggedFileList(files));
+        // Serialize the response
+        let response_json = response.stringify();
+        // Send message to client
+        self.send_message(source_id, &response_json, session_id, route);
+    }
+
+    /// Send the catalog details of the server files of that kind with a `FileCatalog` message
+    pub fn handle_file_catalog(
+        &mut self,
+        kind: ContentKind,
+        source_id: NodeId,
+        session_id: u64,
+        route: &[u8],
+    ) {
+        self.logger.log(
+            format!(
+                "Client {} requested file catalog from server {}\n",
+                source_id, self.server_id
+            )
+            .as_str(),
+            INFO,
+        );
+        let store = self.store(kind);
+        // Describe every file with the metadata of the store
+        let entries = store
+            .list()
+            .into_iter()
+            .filter_map(|id| store.metadata(id))
+            .map(|metadata| CatalogEntry {
+                id: metadata.id,
+                title: metadata.title,
+                description: metadata.description,
+                mime_type: metadata.mime_type,
+                tags: metadata.tags,
+                sha256: metadata.sha256,
+            })
+            .collect();
+
+        // Create a response with the catalog
+        let response = ContentResponseWrapper::Content(ContentResponse::FileCatalog(entries));
+        // Serialize the response
+        let response_json = response.stringify();
+        // Send message to client
+        self.send_message(source_id, &response_json, session_id, route);
+    }
+
+    /// Returns a text file based on the id with a `TextFile` message
+    pub fn handle_file_request(
+        &mut self,
+        id: u8,
+        source_id: NodeId,
+        session_id: u64,
+        route: &[u8],
+    ) {
+        self.logger.log(
+            format!(
+                "Client {} requested a text file from server {}\n",
+                source_id, self.server_id
+            )
+            .as_str(),
+            INFO,
+        );
+        // Stream big files instead of loading them
+        if let Some(stream) = self.text_stream(id) {
+            self.send_stream(source_id, id, stream, session_id, route);
+            return;
+        }
+        // Send the response already serialized for a previous request
+        let key = ResponseKey::TextFile(id);
+        if let Some(request_json) = self.responses.get(&key) {
+            self.logger.log(
+                format!("Text file {id} served from the cache\n").as_str(),
+                DEBUG,
+            );
+            self.send_message(source_id, &request_json, session_id, route);
+            return;
+        }
+        // Read the contents of the file with that id
+        match self.files.get(id) {
+            // Convert the content into a UTF-8 string
+            Ok(file_data) => {
+                let file_string = text::decode(&file_data, self.text_encoding(id, &file_data));
+                // Create a response with text string
+                let request =
+                    BrowserResponseWrapper::Chat(BrowserResponse::TextFile(id, file_string));
+                // Serialize the response
+                let request_json = request.stringify();
+                self.responses.insert(key, request_json.clone());
+                // Send message to client
+                self.send_message(source_id, &request_json, session_id, route);
+            }
+            // If the file can't be read send an error
+            Err(e) => {
+                self.send_error(ContentError::reading(id, &e), source_id, session_id, route);
+            }
+        }
+    }
+
+    /// Returns the ids of the media files referenced by a text file with a `MediaReferences` message
+    pub fn handle_media_references_request(
+        &mut self,
+        id: u8,
+        source_id: NodeId,
+        session_id: u64,
+        route: &[u8],
+    ) {
+        self.logger.log(
+            format!(
+                "Client {} requested the media references of file {} from server {}\n",
+                source_id, id, self.server_id
+            )
+            .as_str(),
+            INFO,
+        );
+        // Use the references found when the file was loaded, or parse the file now
+        let media = match self.files.metadata(id).and_then(|m| m.media_references) {
+            Some(media) => media,
+            None => match self.files.get(id) {
+                Ok(file_data) => {
+                    let encoding = self.text_encoding(id, &file_data);
+                    links::media_references(&text::decode(&file_data, encoding))
+                }
+                // If the file can't be read send an error
+                Err(e) => {
+                    self.send_error(ContentError::reading(id, &e), source_id, session_id, route);
+                    return;
+                }
+            },
+        };
+
+        // Create a response with the referenced media ids
+        let response =
+            ContentResponseWrapper::Content(ContentResponse::MediaReferences { id, media });
+        // Serialize the response
+        let response_json = response.stringify();
+        // Send message to client
+        self.send_message(source_id, &response_json, session_id, route);
+    }
+
+    /// Validates and stores an uploaded file, replying with its new id or with the reason it was refused
+    pub fn handle_upload_request(
+        &mut self,
+        data: Vec<u8>,
+        source_id: NodeId,
+        session_id: u64,
+        route: &[u8],
+    ) {
+        self.logger.log(
+            format!(
+                "Client {} uploaded {} bytes to server {}\n",
+                source_id,
+                data.len(),
+                self.server_id
+            )
+            .as_str(),
+            INFO,
+        );
+        let response = match self.store_upload(&data) {
+            Ok((kind, id)) => {
+                self.uploads.push(id);
+                self.record_versions(Some(&[id]));
+                self.notify_content_changes(
+                    kind,
+                    &ContentChanges {
+                        added: vec![id],
+                        ..ContentChanges::default()
+                    },
+                );
+                ContentResponse::Uploaded(id)
+            }
+            Err(reason) => {
+                self.logger
+                    .log(format!("Upload rejected: {reason}\n").as_str(), ERROR);
+                ContentResponse::UploadRejected(reason)
+            }
+        };
+
+        // Create a response with the outcome of the upload
+        let response = ContentResponseWrapper::Content(response);
+        // Serialize the response
+        let response_json = response.stringify();
+        // Send message to client
+        self.send_message(source_id, &response_json, session_id, route);
+    }
+
+    /// Checks the upload and writes it with the lowest free id, returning its kind and id
+    /// or the reason the upload was refused
+    fn store_upload(&mut self, data: &[u8]) -> Result<(ContentKind, u8), String> {
+        if !self.options.accept_uploads {
+            return Err("Uploads are disabled".to_string());
+        }
+        if data.is_empty() {
+            return Err("The upload is empty".to_string());
+        }
+        if data.len() > self.options.max_upload_bytes {
+            return Err(format!(
+                "The upload is {} bytes, the limit is {} bytes",
+                data.len(),
+                self.options.max_upload_bytes
+            ));
+        }
+        // Check the content matches the server type
+        let (store, extension, kind) = match self.server_type {
+            ServerType::Text => {
+                if std::str::from_utf8(data).is_err() {
+                    return Err("Text uploads must be valid UTF-8".to_string());
+                }
+                (&mut self.files, "txt", ContentKind::Text)
+            }
+            ServerType::Media => {
+                let (_, format) = media::decode(data)
+                    .map_err(|err| format!("The upload is not a readable image: {err}"))?;
+                (
+                    &mut self.media,
+                    format.extensions_str()[0],
+                    ContentKind::Media,
+                )
+            }
+            ServerType::Chat => return Err("This server does not accept uploads".to_string()),
+        };
+        for id in 0..=u8::MAX {
+            if store.contains(id) {
+                continue;
+            }
+            match store.put(id, data, extension) {
+                Ok(()) => return Ok((kind, id)),
+                // A file with that id exists but is not served
+                Err(err) if err.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(err) => return Err(format!("Error storing the upload: {err}")),
+            }
+        }
+        Err("The server has no free ids".to_string())
+    }
+
+    /// Records a new version of the served items whose bytes changed,
+    /// only of the items with the given ids if there are any
+    fn record_versions(&mut self, ids: Option<&[u8]>) {
+        let store = match self.server_type {
+            ServerType::Text => &self.files,
+            ServerType::Media => &self.media,
+            ServerType::Chat => return,
+        };
+        let ids = ids.map_or_else(|| store.list(), <[u8]>::to_vec);
+        for id in ids {
+            let Some(sha256) = store.metadata(id).and_then(|metadata| metadata.sha256) else {
+                continue;
+            };
+            let open = || store.opener(id).and_then(|open| open());
+            if let Err(err) = self.history.record(id, &sha256, open) {
+                self.logger.log(
+                    format!("Error recording a version of '{id}': {err}\n").as_str(),
+                    ERROR,
+                );
+            }
+        }
+    }
+
+    /// Adds the text files to the search index, only the ones with the given ids if there are any
+    fn index_text(&mut self, ids: Option<&[u8]>) {
+        if !self.serves(ContentKind::Text) {
+            return;
+        }
+        let ids = ids.map_or_else(|| self.files.list(), <[u8]>::to_vec);
+        for id in ids {
+            // Big files are read a few lines at a time and their text is not kept
+            let streamed = self
+                .files
+                .metadata(id)
+                .is_some_and(|metadata| metadata.size >= self.options.stream_threshold_bytes);
+            let indexed = if streamed {
+                self.index_text_lines(id)
+            } else {
+                self.files.get(id).map(|file_data| {
+                    let encoding = self.text_encoding(id, &file_data);
+                    self.search_index
+                        .insert(id, text::decode(&file_data, encoding));
+                })
+            };
+            if let Err(e) = indexed {
+                self.logger
+                    .log(format!("Error indexing file '{id}': {e}\n").as_str(), ERROR);
+            }
+        }
+    }
+
+    /// Adds a big text file to the search index without keeping its text, see `index_text`
+    fn index_text_lines(&mut self, id: u8) -> io::Result<()> {
+        let open = self.files.opener(id)?;
+        let recorded = self
+            .files
+            .metadata(id)
+            .and_then(|metadata| metadata.encoding)
+            .and_then(|label| Encoding::for_label(label.as_bytes()));
+        let encoding = match recorded {
+            Some(encoding) => encoding,
+            None => text::read_encoding(
+                open()?,
+                text::encoding_for_label(&self.options.text_fallback_encoding),
+            )?,
+        };
+        let source = open()?;
+        self.search_index
+            .insert_lines(id, |lines| text::decode_lines(source, encoding, lines))
+    }
+
+    /// Returns the text files matching the query, best first, with a `SearchResults` message
+    pub fn handle_search_request(
+        &mut self,
+        query: &str,
+        source_id: NodeId,
+        session_id: u64,
+        route: &[u8],
+    ) {
+        self.logger.log(
+            format!(
+                "Client {} searched '{}' on server {}\n",
+                source_id, query, self.server_id
+            )
+            .as_str(),
+            INFO,
+        );
+        let hits = self
+            .search_index
+            .search(query, self.options.max_search_results);
+
+        // Create a response with the ranked files
+        let response = ContentResponseWrapper::Content(ContentResponse::SearchResults {
+            query: query.to_string(),
+            hits,
+        });
+        // Serialize the response
+        let response_json = response.stringify();
+        // Send message to client
+        self.send_message(source_id, &response_json, session_id, route);
+    }
+
+    /// Returns a slice of the stored bytes of a file with a `Range` message
+    #[allow(clippy::too_many_arguments)]
+    pub fn handle_range_request(
+        &mut self,
+        id: u8,
+        kind: ContentKind,
+        offset: u64,
+        length: Option<u64>,
+        source_id: NodeId,
+        session_id: u64,
+        route: &[u8],
+    ) {
+        self.logger.log(
+            format!(
+                "Client {} requested bytes {}..{:?} of file {} from server {}\n",
+                source_id, offset, length, id, self.server_id
+            )
+            .as_str(),
+            INFO,
+        );
+        match self.store(kind).get_range(id, offset, length) {
+            Ok((data, total_size)) => {
+                // Create a response with the slice
+                let response = ContentResponseWrapper::Content(ContentResponse::Range {
+                    id,
+                    offset,
+                    total_size,
+                    data,
+                });
+                // Serialize the response
+                let response_json = response.stringify();
+                // Send message to client
+                self.send_message(source_id, &response_json, session_id, route);
+            }
+            // If the file can't be read or the range is invalid send an error
+            Err(e) => {
+                self.send_error(ContentError::reading(id, &e), source_id, session_id, route);
+            }
+        }
+    }
+
+    /// Returns the details of a file with a `Metadata` message
+    pub fn handle_metadata_request(
+        &mut self,
+        id: u8,
+        kind: ContentKind,
+        source_id: NodeId,
+        session_id: u64,
+        route: &[u8],
+    ) {
+        self.logger.log(
+            format!(
+                "Client {} requested the metadata of file {} from server {}\n",
+                source_id, id, self.server_id
+            )
+            .as_str(),
+            INFO,
+        );
+        let Some(details) = self.file_details(id, kind) else {
+            self.send_error(ContentError::NotFound(id), source_id, session_id, route);
+            return;
+        };
+
+        // Create a response with the details
+        let response = ContentResponseWrapper::Content(ContentResponse::Metadata(details));
+        // Serialize the response
+        let response_json = response.stringify();
+        // Send message to client
+        self.send_message(source_id, &response_json, session_id, route);
+    }
+
+    /// Sends the details of every file of that kind with a `DetailedFileList` message
+    pub fn handle_detailed_files_list(
+        &mut self,
+        kind: ContentKind,
+        source_id: NodeId,
+        session_id: u64,
+        route: &[u8],
+    ) {
+        self.logger.log(
+            format!(
+                "Client {} requested detailed file list from server {}\n",
+                source_id, self.server_id
+            )
+            .as_str(),
+            INFO,
+        );
+        let details = self
+            .store(kind)
+            .list()
+            .into_iter()
+            .filter_map(|id| self.file_details(id, kind))
+            .collect();
+
+        // Create a response with the details
+        let response = ContentResponseWrapper::Content(ContentResponse::DetailedFileList(details));
+        // Serialize the response
+        let response_json = response.stringify();
+        // Send message to client
+        self.send_message(source_id, &response_json, session_id, route);
+    }
+
+    /// Describes a served file, the dimensions of a media file are read from its header
+    /// and the fragments of the response that would carry it are estimated from its size
+    fn file_details(&self, id: u8, kind: ContentKind) -> Option<FileDetails> {
+        let store = self.store(kind);
+        let metadata = store.metadata(id)?;
+        let dimensions = match kind {
+            ContentKind::Media => match store.opener(id).and_then(|open| open()) {
+                Ok(source) => media::read_dimensions(BufReader::new(source)).ok(),
+                Err(e) => {
+                    error!("Error reading file '{id}': {e}\n");
+                    None
+                }
+            },
+            ContentKind::Text => None,
+        };
+        let response_len = self.response_len_estimate(id, kind, &metadata);
+        Some(FileDetails {
+            id,
+            size: metadata.size,
+            mime_type: metadata.mime_type,
+            dimensions,
+            modified: metadata
+                .modified
+                .map(|modified| DateTime::<Utc>::from(modified).to_rfc3339()),
+            fragments: response_len.div_ceil(FRAGMENT_DSIZE as u64),
+        })
+    }
+
+    /// Returns the length of the response carrying a file: the one already cached, or an estimate
+    /// from the size of the file and the configured media encoding, assuming that the text
+    /// needs no escaping and that the bytes of a media file are evenly distributed
+    fn response_len_estimate(&self, id: u8, kind: ContentKind, metadata: &ContentMetadata) -> u64 {
+        let size = metadata.size;
+        let (key, template, payload_len) = if kind == ContentKind::Text {
+            let template =
+                BrowserResponseWrapper::Chat(BrowserResponse::TextFile(id, String::new()));
+            (ResponseKey::TextFile(id), template.stringify(), size)
+        } else {
+            match self.options.media_encoding {
+                MediaEncoding::Array => {
+                    let template =
+                        BrowserResponseWrapper::Chat(BrowserResponse::MediaFile(id, Vec::new()));
+                    // 256 different bytes take 658 digits (10 of 1, 90 of 2 and 156 of 3),
+                    // plus a comma between the numbers
+                    let payload_len = (size.saturating_mul(658 + 256) / 256).saturating_sub(1);
+                    (
+                        ResponseKey::MediaFile(id),
+                        template.stringify(),
+                        payload_len,
+                    )
+                }
+                MediaEncoding::Base64 => {
+                    let template =
+                        ContentResponseWrapper::Content(ContentResponse::CompactMediaFile {
+                            id,
+                            mime_type: metadata
+                                .mime_type
+                                .clone()
+                                .unwrap_or_else(|| "application/octet-stream".to_string()),
+                            sha256: metadata.sha256.clone(),
+                            data: String::new(),
+                        });
+                    (
+                        ResponseKey::CompactMedia(id),
+                        template.stringify(),
+                        size.div_ceil(3) * 4,
+                    )
+                }
+            }
+        };
+        // The template holds an empty payload, `""` or `[]`, counted in its length
+        self.responses
+            .peek_len(&key)
+            .map_or(template.len() as u64 + payload_len, |len| len as u64)
+    }
+
+    /// Returns a version of a file, the latest one if no version is given,
+    /// with a `FileVersion` message
+    pub fn handle_version_request(
+        &mut self,
+        id: u8,
+        version: Option<u32>,
+        source_id: NodeId,
+        session_id: u64,
+        route: &[u8],
+    ) {
+        self.logger.log(
+            format!(
+                "Client {} requested version {:?} of file {} from server {}\n",
+                source_id, version, id, self.server_id
+            )
+            .as_str(),
+            INFO,
+        );
+        let current = self.history.current_version(id);
+        let Some(version) = version.or(current) else {
+            self.send_error(ContentError::NotFound(id), source_id, session_id, route);
+            return;
+        };
+        // The latest version is the served file, the older ones come from the archive
+        let data = match self.primary_kind() {
+            Some(kind) if Some(version) == current => self.store(kind).get(id),
+            _ => self.history.get(id, version),
+        };
+        match data {
+            Ok(data) => {
+                // Create a response with the bytes of the version
+                let response = ContentResponseWrapper::Content(ContentResponse::FileVersion {
+                    id,
+                    version,
+                    data,
+                });
+                // Serialize the response
+                let response_json = response.stringify();
+                // Send message to client
+                self.send_message(source_id, &response_json, session_id, route);
+            }
+            // If the version does not exist send an error
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                self.send_error(ContentError::NotFound(id), source_id, session_id, route);
+            }
+            Err(e) => {
+                let error = ContentError::InternalError(format!(
+                    "Error reading version {version} of file '{id}': {e}"
+                ));
+                self.send_error(error, source_id, session_id, route);
+            }
+        }
+    }
+
+    /// Sends the ids of the served files with their current version, and the
+    /// available versions of each one, with a `VersionedFileList` message
+    pub fn handle_versioned_files_list(
+        &mut self,
+        source_id: NodeId,
+        session_id: u64,
+        route: &[u8],
+    ) {
+        self.logger.log(
+            format!(
+                "Client {} requested versioned file list from server {}\n",
+                source_id, self.server_id
+            )
+            .as_str(),
+            INFO,
+        );
+        let ids = self
+            .primary_kind()
+            .map(|kind| self.store(kind).list())
+            .unwrap_or_default();
+        let files = ids
+            .into_iter()
+            .map(|id| VersionedFile {
+                id,
+                version: self.history.current_version(id).unwrap_or(1),
+                versions: self.history.versions(id),
+            })
+            .collect();
+
+        // Create a response with the versioned list
+        let response = ContentResponseWrapper::Content(ContentResponse::VersionedFileList(files));
+        // Serialize the response
+        let response_json = response.stringify();
+        // Send message to client
+        self.send_message(source_id, &response_json, session_id, route);
+    }
+
+    /// Returns the stream of the `TextFile` response of a big UTF-8 file without byte order mark,
+    /// the other files are transcoded in memory
+    fn text_stream(&mut self, id: u8) -> Option<ResponseStream> {
+        let metadata = self.files.metadata(id)?;
+        if metadata.size < self.options.stream_threshold_bytes
+            || metadata.encoding.as_deref() != Some("UTF-8")
+            || self
+                .files
+                .get_range(id, 0, Some(3))
+                .is_ok_and(|(start, _)| start == b"\xef\xbb\xbf")
+        {
+            return None;
+        }
+        let template =
+            BrowserResponseWrapper::Chat(BrowserResponse::TextFile(id, String::new())).stringify();
+        self.open_stream(id, &template, "\"\"", Payload::Text)
+    }
+
+    /// Returns the stream of the `MediaFile` or `CompactMediaFile` response
+    /// of a big media file sent untouched
+    fn media_stream(&mut self, id: u8, encoding: MediaEncoding) -> Option<ResponseStream> {
+        let metadata = self.media.metadata(id)?;
+        if metadata.size < self.options.stream_threshold_bytes || !self.options.media_passthrough {
+            return None;
+        }
+        match encoding {
+            MediaEncoding::Array => {
+                let template =
+                    BrowserResponseWrapper::Chat(BrowserResponse::MediaFile(id, Vec::new()))
+                        .stringify();
+                self.open_stream(id, &template, "[]", Payload::Bytes)
+            }
+            MediaEncoding::Base64 => {
+                let template = ContentResponseWrapper::Content(ContentResponse::CompactMediaFile {
+                    id,
+                    mime_type: metadata
+                        .mime_type
+                        .unwrap_or_else(|| "application/octet-stream".to_string()),
+                    sha256: metadata.sha256,
+                    data: String::new(),
+                })
+                .stringify();
+                self.open_stream(id, &template, "\"\"", Payload::Base64)
+            }
+        }
+    }
+
+    /// Returns the media encoding asked by the request of the session, or the configured one
+    fn media_encoding(&self, session_id: u64) -> MediaEncoding {
+        self.media_encodings
+            .get(&session_id)
+            .copied()
+            .unwrap_or(self.options.media_encoding)
+    }
+
+    /// Opens the stream of a response whose payload is the file, given the response
+    /// serialized with an empty payload
+    fn open_stream(
+        &mut self,
+        id: u8,
+        template: &str,
+        placeholder: &str,
+        payload: Payload,
+    ) -> Option<ResponseStream> {
+        let store = match payload {
+            Payload::Text => &self.files,
+            Payload::Bytes | Payload::Base64 => &self.media,
+        };
+        let (prefix, suffix) = ResponseStream::split_response(template, placeholder)?;
+        // The length is measured on the same handle the response is streamed from
+        match store
+            .opener(id)
+            .and_then(|open| open())
+            .and_then(|source| ResponseStream::new(&prefix, &suffix, payload, source))
+        {
+            Ok(stream) => Some(stream),
+            Err(e) => {
                 self.logger.log(
-                    format!("Error deserializing request: {err}, raw content is {raw_content}\n")
-                        .as_str(),
+                    format!("Error streaming file '{id}': {e}\n").as_str(),
                     ERROR,
                 );
+                None
             }
         }
     }
 
-    /// Send a list of the server file IDs with a `FileList` message matching the server type
-    pub fn handle_files_list(&mut self, source_id: NodeId, session_id: u64, route: &[u8]) {
+    /// Drops the cached responses and variants of a content, to be called when it changes
+    pub fn invalidate_content(&mut self, id: u8) {
+        self.responses.invalidate(id);
+    }
+
+    /// Returns the encoding of a text file, the one recorded when it was loaded
+    /// or, if it's missing, the one detected from its content
+    fn text_encoding(&self, id: u8, data: &[u8]) -> &'static Encoding {
+        self.files
+            .metadata(id)
+            .and_then(|metadata| metadata.encoding)
+            .and_then(|label| Encoding::for_label(label.as_bytes()))
+            .unwrap_or_else(|| {
+                text::detect_encoding(
+                    data,
+                    text::encoding_for_label(&self.options.text_fallback_encoding),
+                )
+            })
+    }
+
+    /// Returns a media file based on the id with a `MediaFile` message
+    pub fn handle_media_request(
+        &mut self,
+        id: u8,
+        source_id: NodeId,
+        session_id: u64,
+        route: &[u8],
+    ) {
         self.logger.log(
             format!(
-                "Client {} requested file list from server {} of type {:?}\n",
-                source_id, self.server_id, self.server_type
+                "Client {} requested a media file from server {}\n",
+                source_id, self.server_id
             )
             .as_str(),
             INFO,
         );
-        //Take file IDs from hashmap
-        let file_ids = match self.server_type {
-            ServerType::Text => self.files.keys().copied().collect(),
-            ServerType::Media => self.media.keys().copied().collect(),
-            ServerType::Chat => {
-                self.logger
-                    .log("Error: ServerType::Chat is not supported!\n", ERROR);
-                std::process::exit(1);
-            }
+        let encoding = self.media_encoding(session_id);
+        // Stream big files that are sent untouched instead of loading them
+        if let Some(stream) = self.media_stream(id, encoding) {
+            self.send_stream(source_id, id, stream, session_id, route);
+            return;
+        }
+        // Send the response already serialized for a previous request
+        let key = match encoding {
+            MediaEncoding::Array => ResponseKey::MediaFile(id),
+            MediaEncoding::Base64 => ResponseKey::CompactMedia(id),
         };
+        if let Some(request_json) = self.responses.get(&key) {
+            self.logger.log(
+                format!("Media file {id} served from the cache\n").as_str(),
+                DEBUG,
+            );
+            self.send_message(source_id, &request_json, session_id, route);
+            return;
+        }
+        // Encode the media in its original format
+        match self.encode_media(id, None) {
+            Ok(encoded) => {
+                // Create a response with image vec and serialize it
+                let request_json = match encoding {
+                    MediaEncoding::Array => {
+                        BrowserResponseWrapper::Chat(BrowserResponse::MediaFile(id, encoded.data))
+                            .stringify()
+                    }
+                    MediaEncoding::Base64 => {
+                        let sha256 = self.original_sha256(id, &encoded);
+                        Self::compact_media_response(id, &encoded, sha256).stringify()
+                    }
+                };
+                self.responses.insert(key, request_json.clone());
+                // Send message to client
+                self.send_message(source_id, &request_json, session_id, route);
+            }
+            Err(error) => self.send_error(error, source_id, session_id, route),
+        }
+    }
 
-        // Create a response with file IDs
-        let request = BrowserResponseWrapper::Chat(BrowserResponse::FileList(file_ids));
-        // Serialize the response
-        let request_json = request.stringify();
-        // Send message to client
-        self.send_message(source_id, &request_json, session_id, route);
+    /// Returns the digest of the stored bytes of a media file, if they are the ones encoded
+    fn original_sha256(&self, id: u8, encoded: &EncodedMedia) -> Option<String> {
+        if encoded.original {
+            self.media.metadata(id).and_then(|metadata| metadata.sha256)
+        } else {
+            None
+        }
     }
 
-    /// Returns a text file based on the id with a `TextFile` message
-    pub fn handle_file_request(
+    /// Creates a `CompactMediaFile` response with the media encoded in base64
+    fn compact_media_response(
+        id: u8,
+        encoded: &EncodedMedia,
+        sha256: Option<String>,
+    ) -> ContentResponseWrapper {
+        ContentResponseWrapper::Content(ContentResponse::CompactMediaFile {
+            id,
+            mime_type: encoded.format.to_mime_type().to_string(),
+            sha256,
+            data: messages::encode_media(&encoded.data),
+        })
+    }
+
+    /// Returns a media file based on the id with a `MediaFile` content message,
+    /// the image is encoded in the requested format if it's given, otherwise in its original one
+    pub fn handle_negotiated_media_request(
         &mut self,
         id: u8,
+        format: Option<&str>,
         source_id: NodeId,
         session_id: u64,
         route: &[u8],
     ) {
         self.logger.log(
             format!(
-                "Client {} requested a text file from server {}\n",
-                source_id, self.server_id
+                "Client {} requested media file {} as {:?} from server {}\n",
+                source_id, id, format, self.server_id
             )
             .as_str(),
             INFO,
         );
-        // Search file with that id
-        if let Some(file_path) = self.files.get(&id) {
-            // Read the contents of the file
-            match fs::read(file_path) {
-                // Convert the content into a string
-                Ok(file_data) => {
-                    let file_string = match String::from_utf8(file_data) {
-                        Ok(string) => string,
-                        Err(err) => {
-                            self.logger.log(
-                                format!("Error converting file data to String: {err}\n").as_str(),
-                                ERROR,
-                            );
-                            return;
-                        }
-                    };
-                    // Create a response with text string
-                    let request =
-                        BrowserResponseWrapper::Chat(BrowserResponse::TextFile(id, file_string));
-                    // Serialize the response
-                    let request_json = request.stringify();
-                    // Send message to client
-                    self.send_message(source_id, &request_json, session_id, route);
-                }
-                Err(e) => {
-                    self.logger.log(
-                        format!("Error reading file '{file_path}': {e}\n").as_str(),
-                        ERROR,
-                    );
-                }
+        // Check the requested format
+        let format = match format.map(|name| (name, media::writable_format(name))) {
+            Some((_, Some(format))) => Some(format),
+            Some((name, None)) => {
+                self.send_error(
+                    ContentError::BadRequest(format!("Unsupported media format '{name}'")),
+                    source_id,
+                    session_id,
+                    route,
+                );
+                return;
             }
-        } else {
-            // If the file with that ID does not exist print error
-            self.logger
-                .log(format!("File with ID '{id}' not found\n").as_str(), ERROR);
-        }
+            None => None,
+        };
+        let encoded = match self.encode_media(id, format) {
+            Ok(encoded) => encoded,
+            Err(error) => {
+                self.send_error(error, source_id, session_id, route);
+                return;
+            }
+        };
+        // The digest is sent only when the bytes are the stored ones
+        let sha256 = self.original_sha256(id, &encoded);
+        // Create a response with the image, its MIME type and digest
+        let response = match self.media_encoding(session_id) {
+            MediaEncoding::Array => ContentResponseWrapper::Content(ContentResponse::MediaFile {
+                id,
+                mime_type: encoded.format.to_mime_type().to_string(),
+                sha256,
+                data: encoded.data,
+            }),
+            MediaEncoding::Base64 => Self::compact_media_response(id, &encoded, sha256),
+        };
+        // Serialize the response
+        let response_json = response.stringify();
+        // Send message to client
+        self.send_message(source_id, &response_json, session_id, route);
     }
 
-    /// Returns a media file based on the id with a `MediaFile` message
-    pub fn handle_media_request(
+    /// Returns a resized rendition of a media file with a `MediaVariant` message,
+    /// the variants are rendered on the first request and then served from the cache
+    pub fn handle_variant_request(
         &mut self,
-        id: u8,
+        key: VariantKey,
         source_id: NodeId,
         session_id: u64,
         route: &[u8],
     ) {
         self.logger.log(
             format!(
-                "Client {} requested a media file from server {}\n",
-                source_id, self.server_id
+                "Client {} requested variant {:?} from server {}\n",
+                source_id, key, self.server_id
             )
             .as_str(),
             INFO,
         );
-        // Search file with that id
-        if let Some(media_path) = self.media.get(&id) {
-            // Open the image
-            match image::open(media_path) {
-                Ok(image) => {
-                    // Write image into a vec buffer
-                    let mut buffer = Vec::new();
-                    match image.write_to(&mut Cursor::new(&mut buffer), ImageFormat::Jpeg) {
-                        Ok(()) => {
-                            // Create a response with image vec
-                            let request = BrowserResponseWrapper::Chat(BrowserResponse::MediaFile(
-                                id, buffer,
-                            ));
-                            // Serialize the response
-                            let request_json = request.stringify();
-                            // Send message to client
-                            self.send_message(source_id, &request_json, session_id, route);
-                        }
-                        Err(e) => {
-                            self.logger
-                                .log(format!("Error in image: {e}\n").as_str(), ERROR);
-                        }
-                    }
-                }
-                Err(e) => {
-                    self.logger.log(
-                        format!("Error reading media '{media_path}': {e}\n").as_str(),
-                        ERROR,
-                    );
-                }
-            }
-        } else {
-            // If the file with that ID does not exist print error
-            self.logger
-                .log(format!("Media with ID '{id}' not found\n").as_str(), ERROR);
+        // Check if it's a media server
+        if !self.serves(ContentKind::Media) {
+            self.send_error(
+                ContentError::WrongServerType(
+                    "This server cannot handle media file requests".to_string(),
+                ),
+                source_id,
+                session_id,
+                route,
+            );
+            return;
+        }
+        // Rendered variants are kept with the other cached responses
+        let cache_key = ResponseKey::MediaVariant(key);
+        if let Some(response_json) = self.responses.get(&cache_key) {
+            self.send_message(source_id, &response_json, session_id, route);
+            return;
         }
+        let rendered = self.media.get(key.id).and_then(|data| {
+            Variant::render(&data, key).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+        });
+        let variant = match rendered {
+            Ok(variant) => variant,
+            // If the file with that ID does not exist send an error
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                self.send_error(ContentError::NotFound(key.id), source_id, session_id, route);
+                return;
+            }
+            Err(e) => {
+                let error =
+                    ContentError::InternalError(format!("Error rendering media '{}': {e}", key.id));
+                self.send_error(error, source_id, session_id, route);
+                return;
+            }
+        };
+        // Create a response with the variant
+        let response = ContentResponseWrapper::Content(ContentResponse::MediaVariant {
+            id: key.id,
+            width: variant.width,
+            height: variant.height,
+            data: variant.data,
+        });
+        // Serialize the response
+        let response_json = response.stringify();
+        self.responses.insert(cache_key, response_json.clone());
+        // Send message to client
+        self.send_message(source_id, &response_json, session_id, route);
+    }
+
+    /// Reads the media with that id and encodes it in the given format, or in its original one,
+    /// in passthrough mode the stored bytes are returned as they are if no conversion is needed
+    fn encode_media(
+        &mut self,
+        id: u8,
+        format: Option<ImageFormat>,
+    ) -> Result<EncodedMedia, ContentError> {
+        // Read the bytes of the media with that id and decode the image
+        let decoded = self.media.get(id).and_then(|data| {
+            let original_format = image::guess_format(&data)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            // Send the stored bytes if they already are in the requested format
+            if self.options.media_passthrough
+                && format.is_none_or(|format| format == original_format)
+            {
+                return Ok(EncodedMedia {
+                    data,
+                    format: original_format,
+                    original: true,
+                });
+            }
+            let (image, original_format) =
+                media::decode(&data).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            let format = format.unwrap_or(original_format);
+            // Write image into a vec buffer
+            media::encode(&image, format)
+                .map(|data| EncodedMedia {
+                    data,
+                    format,
+                    original: false,
+                })
+                .map_err(|e| io::Error::other(format!("Error in image: {e}")))
+        });
+        decoded.map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => ContentError::NotFound(id),
+            _ => ContentError::InternalError(format!("Error reading media '{id}': {e}")),
+        })
     }
 
     /// Returns the server type with a `ServerTypeResponse` message
@@ -601,6 +2331,44 @@ impl ContentServer {
         self.send_message(source_id, &request_json, session_id, route);
     }
 
+    /// Send the kinds of content served with a `ServerCapabilities` message
+    fn handle_capabilities_request(&mut self, source_id: NodeId, session_id: u64, route: &[u8]) {
+        self.logger.log(
+            format!(
+                "Client {} requested capabilities from server {}\n",
+                source_id, self.server_id
+            )
+            .as_str(),
+            INFO,
+        );
+        // Create a response with the kinds served
+        let response = ContentResponseWrapper::Content(ContentResponse::ServerCapabilities(
+            self.served_kinds(),
+        ));
+        // Serialize the response
+        let response_json = response.stringify();
+        // Send message to client
+        self.send_message(source_id, &response_json, session_id, route);
+    }
+
+    /// Logs the error and sends it back to the client with an `Error` message,
+    /// so it doesn't wait for a response that will never come
+    fn send_error(
+        &mut self,
+        error: ContentError,
+        source_id: NodeId,
+        session_id: u64,
+        route: &[u8],
+    ) {
+        self.logger.log(format!("{error}\n").as_str(), ERROR);
+        // Create a response with the error
+        let response = ContentResponseWrapper::Content(ContentResponse::Error(error));
+        // Serialize the response
+        let response_json = response.stringify();
+        // Send message to client
+        self.send_message(source_id, &response_json, session_id, route);
+    }
+
     /// Disassembles a message into fragments,
     /// for each fragment it creates a packet and as routing header uses the reverse route of the request,
     /// after which it puts the packets in the list of sent packets and sends them to the first drone
@@ -613,6 +2381,12 @@ impl ContentServer {
             .as_str(),
             INFO,
         );
+        // Compress the message if the client accepts it
+        let compressed = self
+            .encodings
+            .remove(&session_id)
+            .and_then(|encoding| self.compress_message(message, encoding));
+        let message = compressed.as_deref().unwrap_or(message);
         // Disassemble the message into fragments using deassembler
         let fragments = self
             .deassembler
@@ -620,38 +2394,7 @@ impl ContentServer {
 
         // Loop for every fragment generated
         for fragment in fragments {
-            // Create a fragment with the fragment ID
-            let packet = Packet {
-                pack_type: PacketType::MsgFragment(fragment),
-                session_id,
-                routing_header: SourceRoutingHeader {
-                    hop_index: 1,
-                    hops: route.iter().rev().copied().collect(),
-                },
-            };
-            // Insert the packet into sent_packets
-            self.sent_packets
-                .entry(packet.session_id)
-                .or_default()
-                .push(packet.clone());
-            let drone_id = packet.routing_header.hops[1];
-            // Send the package to the designated drone
-            match self.senders.get(&drone_id) {
-                Some(sender) => {
-                    sender.send(packet.clone()).unwrap();
-                }
-                // If there is no sender print error
-                None => {
-                    self.logger.log(
-                        format!(
-                            "Server {}: No sender found for client {}\n",
-                            self.server_id, drone_id
-                        )
-                        .as_str(),
-                        ERROR,
-                    );
-                }
-            }
+            self.send_fragment(fragment, session_id, route);
         }
         // Notify the controller that the packet has been sent
         let _res = self
@@ -661,6 +2404,137 @@ impl ContentServer {
             ));
     }
 
+    /// Returns the message compressed with the encoding and wrapped in an `Encoded` response,
+    /// or `None` if compressing it doesn't make it smaller
+    fn compress_message(&mut self, message: &str, encoding: ContentEncoding) -> Option<String> {
+        let data = match compression::encode_message(message, encoding) {
+            Ok(data) => data,
+            Err(e) => {
+                self.logger.log(
+                    format!("Error compressing a message: {e}\n").as_str(),
+                    ERROR,
+                );
+                return None;
+            }
+        };
+        let compressed = ContentResponseWrapper::Encoded { encoding, data }.stringify();
+        (compressed.len() < message.len()).then_some(compressed)
+    }
+
+    /// Sends a fragment back along the route of the request and keeps it until it's acknowledged
+    fn send_fragment(&mut self, fragment: Fragment, session_id: u64, route: &[u8]) {
+        // Create a fragment with the fragment ID
+        let packet = Packet {
+            pack_type: PacketType::MsgFragment(fragment),
+            session_id,
+            routing_header: SourceRoutingHeader {
+                hop_index: 1,
+                hops: route.iter().rev().copied().collect(),
+            },
+        };
+        // Insert the packet into sent_packets
+        self.sent_packets
+            .entry(packet.session_id)
+            .or_default()
+            .push(packet.clone());
+        let drone_id = packet.routing_header.hops[1];
+        // Send the package to the designated drone
+        match self.senders.get(&drone_id) {
+            Some(sender) => {
+                sender.send(packet.clone()).unwrap();
+            }
+            // If there is no sender print error
+            None => {
+                self.logger.log(
+                    format!(
+                        "Server {}: No sender found for client {}\n",
+                        self.server_id, drone_id
+                    )
+                    .as_str(),
+                    ERROR,
+                );
+            }
+        }
+    }
+
+    /// Starts sending a streamed response, only `stream_window` fragments are sent
+    /// before the first acks arrive, streamed responses are never compressed
+    fn send_stream(
+        &mut self,
+        destination_id: u8,
+        id: u8,
+        stream: ResponseStream,
+        session_id: u64,
+        route: &[u8],
+    ) {
+        let total_fragments = stream.len().div_ceil(FRAGMENT_DSIZE as u64);
+        self.logger.log(
+            format!(
+                "Server {} streaming {} fragments to {}\n",
+                self.server_id, total_fragments, destination_id
+            )
+            .as_str(),
+            INFO,
+        );
+        self.streams.insert(
+            session_id,
+            OutgoingStream {
+                id,
+                stream,
+                route: route.to_vec(),
+                next_index: 0,
+                total_fragments,
+            },
+        );
+        self.continue_stream(session_id);
+    }
+
+    /// Sends the next fragments of a stream until the window of unacknowledged fragments is full
+    fn continue_stream(&mut self, session_id: u64) {
+        loop {
+            let in_flight = self.sent_packets.get(&session_id).map_or(0, Vec::len);
+            if in_flight >= self.options.stream_window.max(1) {
+                return;
+            }
+            let Some(outgoing) = self.streams.get_mut(&session_id) else {
+                return;
+            };
+            // All the fragments have been sent
+            if outgoing.next_index == outgoing.total_fragments {
+                self.streams.remove(&session_id);
+                let _res = self
+                    .sim_controller_sender
+                    .send(SimControllerResponseWrapper::Event(
+                        SimControllerEvent::MessageSent { session_id },
+                    ));
+                return;
+            }
+            let bytes = match outgoing.stream.next_bytes(FRAGMENT_DSIZE) {
+                Ok(bytes) => bytes,
+                Err(e) => {
+                    let (id, route) = (outgoing.id, outgoing.route.clone());
+                    self.logger
+                        .log(format!("Error streaming a file: {e}\n").as_str(), ERROR);
+                    // The fragments already sent can't be completed, the client is told why
+                    self.forget_session(session_id);
+                    self.send_session_error(ContentError::reading(id, &e), route[0], &route);
+                    return;
+                }
+            };
+            let mut data = [0; FRAGMENT_DSIZE];
+            data[..bytes.len()].copy_from_slice(&bytes);
+            let fragment = Fragment {
+                fragment_index: outgoing.next_index,
+                total_n_fragments: outgoing.total_fragments,
+                length: bytes.len() as u8,
+                data,
+            };
+            outgoing.next_index += 1;
+            let route = outgoing.route.clone();
+            self.send_fragment(fragment, session_id, &route);
+        }
+    }
+
     /// When an ack arrives for a sent packet the corresponding packet is removed from `sent_packets`
     #[allow(dead_code)]
     fn on_ack_arrived(&mut self, ack: &Ack, packet: &Packet) {
@@ -673,6 +2547,8 @@ impl ContentServer {
             DEBUG,
         );
 
+        self.retries
+            .remove(&(packet.session_id, ack.fragment_index));
         if let Some(fragments) = self.sent_packets.get_mut(&packet.session_id) {
             fragments.retain(|packet| match &packet.pack_type {
                 PacketType::MsgFragment(fragment) => fragment.fragment_index != ack.fragment_index,
@@ -681,8 +2557,13 @@ impl ContentServer {
 
             if fragments.is_empty() {
                 self.sent_packets.remove(&packet.session_id);
+                self.error_sessions.remove(&packet.session_id);
             }
         }
+        // Send the next fragments of a streamed response
+        if self.streams.contains_key(&packet.session_id) {
+            self.continue_stream(packet.session_id);
+        }
     }
 
     /// It takes a copy of the packet corresponding to the nack from the list of sent packets,
@@ -706,6 +2587,26 @@ impl ContentServer {
                     .find(|packet| packet.get_fragment_index() == nack.fragment_index)
                     .cloned()
                 {
+                    // Give up on a fragment that keeps failing
+                    let retries = self
+                        .retries
+                        .entry((packet.session_id, nack.fragment_index))
+                        .or_insert(0);
+                    *retries += 1;
+                    if *retries > self.options.max_fragment_retries {
+                        self.logger.log(
+                            &format!(
+                                "Server {} gave up fragment {} of session {} after {} retries\n",
+                                self.server_id,
+                                nack.fragment_index,
+                                packet.session_id,
+                                self.options.max_fragment_retries
+                            ),
+                            ERROR,
+                        );
+                        self.abort_session(packet.session_id, &sent_packet_clone);
+                        return;
+                    }
                     match nack.nack_type {
                         // Resend packet on the same route
                         NackType::Dropped => {
@@ -744,6 +2645,53 @@ impl ContentServer {
         }
     }
 
+    /// Drops every fragment and the stream of a session whose fragment was given up,
+    /// and sends a `SessionAborted` error to the client on a new route so it stops waiting for the response
+    fn abort_session(&mut self, session_id: u64, sent_packet: &Packet) {
+        self.forget_session(session_id);
+        // The error itself can fail, it's not sent again
+        if self.error_sessions.remove(&session_id) {
+            return;
+        }
+        let Some(client_id) = sent_packet.routing_header.destination() else {
+            return;
+        };
+        let route = compute_route_dijkstra(&mut self.topology, self.server_id, client_id);
+        if route.len() < 2 {
+            self.logger.log(
+                &format!(
+                    "Server {} found no route to tell client {} that session {} was given up\n",
+                    self.server_id, client_id, session_id
+                ),
+                ERROR,
+            );
+            return;
+        }
+        // Errors are sent back along the reverse of the request route
+        let request_route: Vec<u8> = route.into_iter().rev().collect();
+        let error = ContentError::SessionAborted(session_id);
+        self.send_session_error(error, client_id, &request_route);
+    }
+
+    /// Drops the fragments, the stream and the retries of a response that is given up
+    fn forget_session(&mut self, session_id: u64) {
+        self.sent_packets.remove(&session_id);
+        self.streams.remove(&session_id);
+        self.retries
+            .retain(|(retry_session, _), _| *retry_session != session_id);
+        self.packet_to_retry
+            .retain(|(retry_session, _)| *retry_session != session_id);
+        self.media_encodings.remove(&session_id);
+    }
+
+    /// Sends an error about a response that was given up under a new session id,
+    /// the client may hold fragments of the given up response that the error can't be assembled with
+    fn send_session_error(&mut self, error: ContentError, client_id: NodeId, route: &[u8]) {
+        let session_id = self.rng.gen();
+        self.error_sessions.insert(session_id);
+        self.send_error(error, client_id, session_id, route);
+    }
+
     /// It takes a packet as input and calculates the route,
     /// if it doesn't find it it puts it in a waiting queue and sends a flood request
     /// otherwise it sends it to the first drone
@@ -914,18 +2862,17 @@ impl ContentServer {
     }
 
     /// Update the topology based on the fragment packets header that arrives
-    fn update_topology_from_packet(&mut self, header:&SourceRoutingHeader) {
-        for (i,&node) in header.hops.iter().enumerate(){
-            
+    fn update_topology_from_packet(&mut self, header: &SourceRoutingHeader) {
+        for (i, &node) in header.hops.iter().enumerate() {
             if !self.topology.nodes().contains(&node) {
                 self.topology.add_node(node);
 
                 let node_type = if i == 0 {
-                    "client".to_string() 
+                    "client".to_string()
                 } else if i == header.hops.len() - 1 {
-                    "server".to_string() 
+                    "server".to_string()
                 } else {
-                    "drone".to_string() 
+                    "drone".to_string()
                 };
 
                 self.topology.set_node_type(node, node_type);
@@ -980,7 +2927,7 @@ impl ContentServer {
     pub fn send_flood_request(&mut self) {
         #[allow(clippy::cast_sign_loss)]
         let now = Utc::now().timestamp_millis() as u128;
-        let timeout = u128::from(TIMEOUT_BETWEEN_FLOODS_MS);
+        let timeout = self.options.flood_interval.as_millis();
 
         if self.flood_time + timeout > now {
             self.logger.log(
@@ -998,21 +2945,24 @@ impl ContentServer {
             format!("Server {} send flood request\n", self.server_id).as_str(),
             INFO,
         );
-        // Loop through all senders and send a flood request to each one
-        for sender in &self.senders {
+        // Loop through all senders, in id order so a seeded run is reproducible,
+        // and send a flood request to each one
+        let mut neighbors: Vec<&u8> = self.senders.keys().collect();
+        neighbors.sort_unstable();
+        for neighbor_id in neighbors {
             let packet = Packet {
                 pack_type: PacketType::FloodRequest(FloodRequest {
                     initiator_id: self.server_id,
-                    flood_id: rand::random(),
+                    flood_id: self.rng.gen(),
                     path_trace: vec![(self.server_id, NodeType::Server)],
                 }),
-                session_id: rand::random(),
+                session_id: self.rng.gen(),
                 routing_header: SourceRoutingHeader {
                     hop_index: 1,
                     hops: Vec::new(),
                 },
             };
-            sender.1.send(packet).unwrap();
+            self.senders[neighbor_id].send(packet).unwrap();
         }
         // Notify the controller indicating that the flood request has been sent
         self.sim_controller_sender
@@ -1063,4 +3013,4 @@ impl ContentServer {
             }
         }
     }
-}
\ No newline at end of file
+}
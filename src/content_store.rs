@@ -0,0 +1,538 @@
+use crate::links;
+use crate::manifest::{Manifest, ManifestEntry};
+use crate::stream::{OpenSource, Source};
+use crate::text;
+use encoding_rs::{Encoding, UTF_8};
+use image::ImageFormat;
+use log::error;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::io::{BufReader, Cursor, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use std::{fs, io};
+
+/// Information about a content item held by a `ContentStore`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ContentMetadata {
+    pub id: u8,
+    pub size: u64,
+    pub path: Option<PathBuf>,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub mime_type: Option<String>,
+    pub tags: Vec<String>,
+    /// Hex SHA-256 digest of the bytes, computed when the item is loaded
+    pub sha256: Option<String>,
+    /// Name of the encoding of a text item, detected when the item is loaded
+    pub encoding: Option<String>,
+    /// Ids of the media files referenced by a text item, in order of first appearance
+    pub media_references: Option<Vec<u8>>,
+    /// Last time the item was changed
+    pub modified: Option<SystemTime>,
+    /// Size and modification time of the file when its digest was computed
+    pub stamp: Option<FileStamp>,
+}
+
+/// Size and modification time of a file, a file whose stamp did not change
+/// is assumed to have the same content
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileStamp {
+    pub size: u64,
+    pub modified: SystemTime,
+}
+
+impl FileStamp {
+    /// Returns the current stamp of the file, `None` if it can't be read
+    pub fn of(path: &Path) -> Option<FileStamp> {
+        let metadata = fs::metadata(path).ok()?;
+        Some(FileStamp {
+            size: metadata.len(),
+            modified: metadata.modified().ok()?,
+        })
+    }
+}
+
+impl ContentMetadata {
+    /// Fills the descriptive fields with the ones of the manifest entry
+    pub fn describe(&mut self, entry: &ManifestEntry) {
+        self.title.clone_from(&entry.title);
+        self.description.clone_from(&entry.description);
+        if entry.mime_type.is_some() {
+            self.mime_type.clone_from(&entry.mime_type);
+        }
+        self.tags.clone_from(&entry.tags);
+    }
+}
+
+/// Returns the ids of the media files referenced by a text item,
+/// decoded with the encoding recorded in its metadata or as UTF-8
+fn media_references(data: &[u8], metadata: &ContentMetadata) -> Vec<u8> {
+    let encoding = metadata
+        .encoding
+        .as_deref()
+        .and_then(|label| Encoding::for_label(label.as_bytes()))
+        .unwrap_or(UTF_8);
+    links::media_references(&text::decode(data, encoding))
+}
+
+/// Returns the ids of the media files referenced by a text item like `media_references`,
+/// reading it a few lines at a time: references split across lines are not found
+fn read_media_references(reader: impl Read, metadata: &ContentMetadata) -> io::Result<Vec<u8>> {
+    let encoding = metadata
+        .encoding
+        .as_deref()
+        .and_then(|label| Encoding::for_label(label.as_bytes()))
+        .unwrap_or(UTF_8);
+    let mut references = Vec::new();
+    text::decode_lines(reader, encoding, |lines| {
+        for id in links::media_references(lines) {
+            if !references.contains(&id) {
+                references.push(id);
+            }
+        }
+    })?;
+    Ok(references)
+}
+
+/// Returns the hex SHA-256 digest of the data
+pub fn sha256_hex(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+/// Returns the hex SHA-256 digest of the bytes read, in chunks, from the reader
+/// # Errors
+/// Returns the error raised reading the bytes
+pub fn read_sha256_hex(mut reader: impl Read) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut reader, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Opens a file for reading through a buffer
+fn open_file(path: &Path) -> io::Result<BufReader<fs::File>> {
+    fs::File::open(path).map(BufReader::new)
+}
+
+/// Guesses the MIME type of a file from its extension
+pub fn mime_type_from_path(path: &Path) -> Option<String> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    if extension == "txt" {
+        return Some("text/plain".to_string());
+    }
+    ImageFormat::from_extension(extension).map(|format| format.to_mime_type().to_string())
+}
+
+/// Source of the content served by a `ContentServer`, items are addressed by their id
+pub trait ContentStore: Send {
+    /// Returns the ids of the items in the store, sorted
+    fn list(&self) -> Vec<u8>;
+
+    /// Returns the bytes of the item with that id
+    /// # Errors
+    /// Returns `NotFound` if the id is not in the store, or the error raised reading the item
+    fn get(&self, id: u8) -> io::Result<Vec<u8>>;
+
+    /// Returns the metadata of the item with that id
+    fn metadata(&self, id: u8) -> Option<ContentMetadata>;
+
+    /// Checks if the store has an item with that id
+    fn contains(&self, id: u8) -> bool {
+        self.list().contains(&id)
+    }
+
+    /// Returns at most `length` bytes of the item starting at `offset`, all the remaining bytes
+    /// if `length` is `None`, together with the total size of the item
+    /// # Errors
+    /// Returns `NotFound` if the id is not in the store, `InvalidInput` if the offset
+    /// is past the end of the item, or the error raised reading the item
+    fn get_range(&self, id: u8, offset: u64, length: Option<u64>) -> io::Result<(Vec<u8>, u64)> {
+        let data = self.get(id)?;
+        let total = data.len() as u64;
+        let (start, end) = range_bounds(offset, length, total)?;
+        Ok((data[start as usize..end as usize].to_vec(), total))
+    }
+
+    /// Returns a function that opens the item for reading, used to stream it in chunks
+    /// # Errors
+    /// Returns `NotFound` if the id is not in the store, or the error raised reading the item
+    fn opener(&self, id: u8) -> io::Result<OpenSource> {
+        let data = self.get(id)?;
+        Ok(Box::new(move || {
+            Ok(Box::new(Cursor::new(data.clone())) as Box<dyn Source>)
+        }))
+    }
+
+    /// Adds a new item with that id, stores backed by files write it with the given extension
+    /// # Errors
+    /// Returns `AlreadyExists` if the id is taken, or the error raised writing the item
+    fn put(&mut self, id: u8, data: &[u8], extension: &str) -> io::Result<()>;
+}
+
+/// Error returned when an id is not in a store
+pub(crate) fn not_found(id: u8) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("content with ID '{id}' not found"),
+    )
+}
+
+/// Returns the start and end of a range of an item of that size, the end is clamped to the size
+/// # Errors
+/// Returns `InvalidInput` if the offset is past the end of the item
+pub(crate) fn range_bounds(offset: u64, length: Option<u64>, total: u64) -> io::Result<(u64, u64)> {
+    if offset > total {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("offset {offset} is past the end of the content ({total} bytes)"),
+        ));
+    }
+    let end = length.map_or(total, |length| offset.saturating_add(length).min(total));
+    Ok((offset, end))
+}
+
+/// Error returned when an id is already taken in a store
+pub(crate) fn already_exists(id: u8) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::AlreadyExists,
+        format!("content with ID '{id}' already exists"),
+    )
+}
+
+/// Store that reads the content from files on disk
+#[derive(Debug, Default)]
+pub struct FileSystemStore {
+    entries: HashMap<u8, ContentMetadata>,
+    directory: Option<PathBuf>,
+}
+
+impl FileSystemStore {
+    /// Returns a store serving the files at the given paths,
+    /// each file is read once to compute its digest
+    pub fn new(entries: HashMap<u8, PathBuf>) -> Self {
+        Self::rescan(entries, None)
+    }
+
+    /// Returns a store serving the files at the given paths like `new`, the files of `previous`
+    /// whose path, size and modification time did not change keep their digest, encoding
+    /// and media references instead of being read again
+    pub fn rescan(entries: HashMap<u8, PathBuf>, previous: Option<&dyn ContentStore>) -> Self {
+        let entries = entries
+            .into_iter()
+            .map(|(id, path)| {
+                // Stamp the file before reading it, so a change during the read is seen next time
+                let stamp = FileStamp::of(&path);
+                let mut metadata = ContentMetadata {
+                    id,
+                    mime_type: mime_type_from_path(&path),
+                    stamp,
+                    ..ContentMetadata::default()
+                };
+                let unchanged = previous
+                    .and_then(|previous| previous.metadata(id))
+                    .filter(|old| {
+                        stamp.is_some() && old.stamp == stamp && old.path.as_ref() == Some(&path)
+                    });
+                if let Some(old) = unchanged {
+                    metadata.sha256 = old.sha256;
+                    metadata.encoding = old.encoding;
+                    metadata.media_references = old.media_references;
+                } else {
+                    metadata.sha256 = match open_file(&path).and_then(read_sha256_hex) {
+                        Ok(sha256) => Some(sha256),
+                        Err(err) => {
+                            error!("Error reading file '{}': {err}\n", path.display());
+                            None
+                        }
+                    };
+                }
+                metadata.path = Some(path);
+                (id, metadata)
+            })
+            .collect();
+        FileSystemStore {
+            entries,
+            directory: None,
+        }
+    }
+
+    /// Sets the directory where the new files are written
+    #[must_use]
+    pub fn with_directory(mut self, directory: &Path) -> Self {
+        self.directory = Some(directory.to_path_buf());
+        self
+    }
+
+    /// Lists the files in the directory with one of the given extensions,
+    /// the id of each file is parsed from its name (e.g. `0001.txt` is `1`)
+    pub fn scan(directory: &Path, extensions: &[&str]) -> Vec<(u8, PathBuf)> {
+        let (found, invalid) = Self::scan_names(directory, extensions);
+        for path in invalid {
+            error!(
+                "Warning: Failed to parse ID from filename '{}'\n",
+                path.display()
+            );
+        }
+        found
+    }
+
+    /// Lists the files in the directory with one of the given extensions like `scan`,
+    /// also returning the files whose name is not an id, sorted by path
+    pub fn scan_names(directory: &Path, extensions: &[&str]) -> (Vec<(u8, PathBuf)>, Vec<PathBuf>) {
+        let mut found = Vec::new();
+        let mut invalid = Vec::new();
+        if let Ok(entries) = fs::read_dir(directory) {
+            for entry in entries.filter_map(Result::ok) {
+                let path = entry.path();
+                // Check extension
+                if !path.extension().is_some_and(|ext| {
+                    extensions
+                        .iter()
+                        .any(|extension| ext.eq_ignore_ascii_case(extension))
+                }) {
+                    continue;
+                }
+                // Parse the numeric name
+                match path
+                    .file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .and_then(|stem| stem.parse::<u8>().ok())
+                {
+                    Some(id) => found.push((id, path)),
+                    None => invalid.push(path),
+                }
+            }
+        }
+        invalid.sort();
+        (found, invalid)
+    }
+
+    /// Adds the titles, descriptions, MIME types and tags of the manifest to the served files
+    pub fn apply_manifest(&mut self, manifest: &Manifest) {
+        for (id, metadata) in &mut self.entries {
+            if let Some(entry) = manifest.get(*id) {
+                metadata.describe(entry);
+            }
+        }
+    }
+
+    /// Detects and records the encoding of each file, files that are not valid UTF-8
+    /// and have no byte order mark are in the fallback encoding.
+    /// The files whose encoding is already known are skipped
+    pub fn detect_encodings(&mut self, fallback: &'static Encoding) {
+        for metadata in self.entries.values_mut() {
+            let (Some(path), None) = (&metadata.path, &metadata.encoding) else {
+                continue;
+            };
+            match open_file(path).and_then(|file| text::read_encoding(file, fallback)) {
+                Ok(encoding) => {
+                    metadata.encoding = Some(encoding.name().to_string());
+                }
+                Err(err) => {
+                    error!("Error reading file '{}': {err}\n", path.display());
+                }
+            }
+        }
+    }
+
+    /// Parses each file and records the media files it references,
+    /// call it after `detect_encodings` so the files are decoded correctly.
+    /// The files already indexed are skipped
+    pub fn index_media_references(&mut self) {
+        for metadata in self.entries.values_mut() {
+            let (Some(path), None) = (&metadata.path, &metadata.media_references) else {
+                continue;
+            };
+            match open_file(path).and_then(|file| read_media_references(file, metadata)) {
+                Ok(references) => {
+                    metadata.media_references = Some(references);
+                }
+                Err(err) => {
+                    error!("Error reading file '{}': {err}\n", path.display());
+                }
+            }
+        }
+    }
+
+    fn path(&self, id: u8) -> io::Result<&PathBuf> {
+        self.entries
+            .get(&id)
+            .and_then(|metadata| metadata.path.as_ref())
+            .ok_or_else(|| not_found(id))
+    }
+}
+
+impl ContentStore for FileSystemStore {
+    fn list(&self) -> Vec<u8> {
+        let mut ids: Vec<u8> = self.entries.keys().copied().collect();
+        ids.sort_unstable();
+        ids
+    }
+
+    fn get(&self, id: u8) -> io::Result<Vec<u8>> {
+        fs::read(self.path(id)?)
+    }
+
+    fn opener(&self, id: u8) -> io::Result<OpenSource> {
+        let path = self.path(id)?.clone();
+        Ok(Box::new(move || {
+            Ok(Box::new(open_file(&path)?) as Box<dyn Source>)
+        }))
+    }
+
+    fn get_range(&self, id: u8, offset: u64, length: Option<u64>) -> io::Result<(Vec<u8>, u64)> {
+        // Read only the requested bytes
+        let mut file = fs::File::open(self.path(id)?)?;
+        let total = file.metadata()?.len();
+        let (start, end) = range_bounds(offset, length, total)?;
+        file.seek(SeekFrom::Start(start))?;
+        let mut data = Vec::new();
+        file.take(end - start).read_to_end(&mut data)?;
+        Ok((data, total))
+    }
+
+    fn metadata(&self, id: u8) -> Option<ContentMetadata> {
+        let mut metadata = self.entries.get(&id)?.clone();
+        if let Some(path) = &metadata.path {
+            let file_metadata = fs::metadata(path).ok();
+            metadata.size = file_metadata.as_ref().map_or(0, fs::Metadata::len);
+            metadata.modified = file_metadata.and_then(|meta| meta.modified().ok());
+        }
+        Some(metadata)
+    }
+
+    fn contains(&self, id: u8) -> bool {
+        self.entries.contains_key(&id)
+    }
+
+    fn put(&mut self, id: u8, data: &[u8], extension: &str) -> io::Result<()> {
+        let Some(directory) = &self.directory else {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "the store has no directory to write to",
+            ));
+        };
+        // Never overwrite a file, even one that is not served
+        let taken_on_disk = fs::read_dir(directory)?
+            .filter_map(Result::ok)
+            .any(|entry| {
+                entry
+                    .path()
+                    .file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .and_then(|stem| stem.parse::<u8>().ok())
+                    == Some(id)
+            });
+        if self.entries.contains_key(&id) || taken_on_disk {
+            return Err(already_exists(id));
+        }
+        let path = directory.join(format!("{id:04}.{extension}"));
+        let mut file = fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)?;
+        file.write_all(data)?;
+        let metadata = ContentMetadata {
+            id,
+            size: data.len() as u64,
+            mime_type: mime_type_from_path(&path),
+            stamp: FileStamp::of(&path),
+            path: Some(path),
+            sha256: Some(sha256_hex(data)),
+            ..ContentMetadata::default()
+        };
+        self.entries.insert(id, metadata);
+        Ok(())
+    }
+}
+
+/// Store that keeps the content in memory, used by tests and by deployments without a filesystem
+#[derive(Debug, Default, Clone)]
+pub struct MemoryStore {
+    items: HashMap<u8, (Vec<u8>, ContentMetadata)>,
+}
+
+impl MemoryStore {
+    /// Returns an empty store
+    pub fn new() -> Self {
+        MemoryStore::default()
+    }
+
+    /// Adds an item to the store, replacing the previous one with the same id
+    pub fn insert(&mut self, id: u8, data: impl Into<Vec<u8>>) {
+        let data = data.into();
+        let metadata = ContentMetadata {
+            id,
+            size: data.len() as u64,
+            sha256: Some(sha256_hex(&data)),
+            modified: Some(SystemTime::now()),
+            ..ContentMetadata::default()
+        };
+        self.items.insert(id, (data, metadata));
+    }
+
+    /// Removes an item from the store and returns its bytes
+    pub fn remove(&mut self, id: u8) -> Option<Vec<u8>> {
+        self.items.remove(&id).map(|(data, _)| data)
+    }
+
+    /// Adds the titles, descriptions, MIME types and tags of the manifest to the stored items
+    pub fn apply_manifest(&mut self, manifest: &Manifest) {
+        for (id, (_, metadata)) in &mut self.items {
+            if let Some(entry) = manifest.get(*id) {
+                metadata.describe(entry);
+            }
+        }
+    }
+
+    /// Detects and records the encoding of each item, items that are not valid UTF-8
+    /// and have no byte order mark are in the fallback encoding
+    pub fn detect_encodings(&mut self, fallback: &'static Encoding) {
+        for (data, metadata) in self.items.values_mut() {
+            let encoding = text::detect_encoding(data, fallback);
+            metadata.encoding = Some(encoding.name().to_string());
+        }
+    }
+
+    /// Parses each item and records the media files it references,
+    /// call it after `detect_encodings` so the items are decoded correctly
+    pub fn index_media_references(&mut self) {
+        for (data, metadata) in self.items.values_mut() {
+            metadata.media_references = Some(media_references(data, metadata));
+        }
+    }
+}
+
+impl ContentStore for MemoryStore {
+    fn list(&self) -> Vec<u8> {
+        let mut ids: Vec<u8> = self.items.keys().copied().collect();
+        ids.sort_unstable();
+        ids
+    }
+
+    fn get(&self, id: u8) -> io::Result<Vec<u8>> {
+        self.items
+            .get(&id)
+            .map(|(data, _)| data.clone())
+            .ok_or_else(|| not_found(id))
+    }
+
+    fn metadata(&self, id: u8) -> Option<ContentMetadata> {
+        self.items.get(&id).map(|(_, metadata)| metadata.clone())
+    }
+
+    fn contains(&self, id: u8) -> bool {
+        self.items.contains_key(&id)
+    }
+
+    fn put(&mut self, id: u8, data: &[u8], extension: &str) -> io::Result<()> {
+        if self.items.contains_key(&id) {
+            return Err(already_exists(id));
+        }
+        self.insert(id, data);
+        if let Some((_, metadata)) = self.items.get_mut(&id) {
+            metadata.mime_type = mime_type_from_path(Path::new(&format!("{id}.{extension}")));
+        }
+        Ok(())
+    }
+}
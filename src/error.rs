@@ -0,0 +1,59 @@
+use std::path::PathBuf;
+use std::{fmt, io};
+
+/// Why a `ContentServer` could not be created
+#[derive(Debug)]
+pub enum ContentServerError {
+    /// The current directory, that the content directories are relative to, can't be read
+    CurrentDirectory(io::Error),
+    /// A content directory of the server does not exist
+    DirectoryMissing(PathBuf),
+    /// The server type can't be used with the given options
+    UnsupportedType(String),
+    /// The content directory has no file to serve
+    NoContent(PathBuf),
+    /// A file in a content directory has no numeric id in its name, and no manifest entry,
+    /// only raised with the `strict_file_names` option
+    InvalidFileName(PathBuf),
+    /// The configuration file can't be read or parsed
+    InvalidConfig(String),
+    /// The builder was not given a channel the server needs
+    MissingChannel(&'static str),
+}
+
+impl fmt::Display for ContentServerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ContentServerError::CurrentDirectory(err) => {
+                write!(f, "Failed to get current directory: {err}")
+            }
+            ContentServerError::DirectoryMissing(directory) => write!(
+                f,
+                "Content directory '{}' does not exist!",
+                directory.display()
+            ),
+            ContentServerError::UnsupportedType(message)
+            | ContentServerError::InvalidConfig(message) => write!(f, "{message}"),
+            ContentServerError::NoContent(directory) => write!(
+                f,
+                "Content directory '{}' has no files to serve",
+                directory.display()
+            ),
+            ContentServerError::InvalidFileName(path) => {
+                write!(f, "Failed to parse ID from filename '{}'", path.display())
+            }
+            ContentServerError::MissingChannel(channel) => {
+                write!(f, "No {channel} channel given to the server")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ContentServerError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ContentServerError::CurrentDirectory(err) => Some(err),
+            _ => None,
+        }
+    }
+}
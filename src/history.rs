@@ -0,0 +1,218 @@
+use crate::content_store::{not_found, read_sha256_hex};
+use log::error;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufReader, Read};
+use std::path::{Path, PathBuf};
+
+/// Name of the file, in the archive directory, holding the digest of every archived version
+pub const INDEX_FILE: &str = "index.json";
+
+/// A version of a content item
+#[derive(Debug, Clone)]
+struct Version {
+    number: u32,
+    sha256: String,
+    /// Whether the bytes of the version are in the archive directory
+    archived: bool,
+}
+
+/// Entry of the index of an archive directory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct IndexEntry {
+    id: u8,
+    number: u32,
+    sha256: String,
+}
+
+/// Numbered versions of each content item, a new version is recorded every time the bytes change.
+/// Only the numbers and the digests are kept in memory, the bytes of the latest version are the
+/// served file; with a directory, the versions that follow a change are archived there as files
+/// named `<id>.<version>`, with their digests listed in an index file
+#[derive(Debug)]
+pub struct ContentHistory {
+    directory: Option<PathBuf>,
+    max_versions: usize,
+    versions: HashMap<u8, Vec<Version>>,
+}
+
+impl ContentHistory {
+    /// Returns a history without an archive, only the latest version of each item can be read,
+    /// from the served file; it remembers the numbers of at most `max_versions` versions
+    pub fn in_memory(max_versions: usize) -> Self {
+        ContentHistory {
+            directory: None,
+            max_versions,
+            versions: HashMap::new(),
+        }
+    }
+
+    /// Returns a history archived in the directory, loading the versions already there:
+    /// their digests are taken from the index, that also lists the versions never archived,
+    /// only the archived versions missing from it are read
+    pub fn in_directory(directory: &Path, max_versions: usize) -> Self {
+        let mut history = ContentHistory {
+            directory: Some(directory.to_path_buf()),
+            max_versions,
+            versions: HashMap::new(),
+        };
+        let index: HashMap<(u8, u32), String> = fs::read(directory.join(INDEX_FILE))
+            .ok()
+            .and_then(|raw| serde_json::from_slice::<Vec<IndexEntry>>(&raw).ok())
+            .unwrap_or_default()
+            .into_iter()
+            .map(|entry| ((entry.id, entry.number), entry.sha256))
+            .collect();
+        for ((id, number), sha256) in &index {
+            history.versions.entry(*id).or_default().push(Version {
+                number: *number,
+                sha256: sha256.clone(),
+                archived: directory.join(format!("{id}.{number}")).exists(),
+            });
+        }
+        let mut unindexed = false;
+        if let Ok(entries) = fs::read_dir(directory) {
+            for path in entries.filter_map(Result::ok).map(|entry| entry.path()) {
+                let Some((id, number)) = parse_version_name(&path) else {
+                    continue;
+                };
+                if index.contains_key(&(id, number)) {
+                    continue;
+                }
+                let sha256 = match fs::File::open(&path)
+                    .map(BufReader::new)
+                    .and_then(read_sha256_hex)
+                {
+                    Ok(sha256) => sha256,
+                    Err(err) => {
+                        error!("Error reading version '{}': {err}\n", path.display());
+                        continue;
+                    }
+                };
+                unindexed = true;
+                history.versions.entry(id).or_default().push(Version {
+                    number,
+                    sha256,
+                    archived: true,
+                });
+            }
+        }
+        for versions in history.versions.values_mut() {
+            versions.sort_by_key(|version| version.number);
+        }
+        if unindexed {
+            if let Err(err) = history.write_index() {
+                error!("Error writing version index: {err}\n");
+            }
+        }
+        history
+    }
+
+    /// Writes the digests of the archived versions to the index file
+    fn write_index(&self) -> io::Result<()> {
+        let Some(directory) = &self.directory else {
+            return Ok(());
+        };
+        let mut entries: Vec<IndexEntry> = self
+            .versions
+            .iter()
+            .flat_map(|(id, versions)| {
+                versions.iter().map(|version| IndexEntry {
+                    id: *id,
+                    number: version.number,
+                    sha256: version.sha256.clone(),
+                })
+            })
+            .collect();
+        entries.sort_by_key(|entry| (entry.id, entry.number));
+        let raw = serde_json::to_vec(&entries).map_err(io::Error::other)?;
+        fs::create_dir_all(directory)?;
+        fs::write(directory.join(INDEX_FILE), raw)
+    }
+
+    /// Records the current bytes of an item, identified by their digest, and returns its version:
+    /// a new version is recorded only if the digest differs from the latest one.
+    /// The first version seen of an item is never copied, a version that follows a change
+    /// is copied to the archive directory in chunks if there is one, `open` is only called then
+    /// # Errors
+    /// Returns the error raised reading or archiving the bytes
+    pub fn record<R: Read>(
+        &mut self,
+        id: u8,
+        sha256: &str,
+        open: impl FnOnce() -> io::Result<R>,
+    ) -> io::Result<u32> {
+        let versions = self.versions.entry(id).or_default();
+        let latest = versions.last();
+        if let Some(latest) = latest.filter(|latest| latest.sha256 == sha256) {
+            return Ok(latest.number);
+        }
+        let number = latest.map_or(1, |latest| latest.number + 1);
+        let archived = match &self.directory {
+            Some(directory) if latest.is_some() => {
+                let mut source = open()?;
+                fs::create_dir_all(directory)?;
+                let mut file = fs::File::create(directory.join(format!("{id}.{number}")))?;
+                io::copy(&mut source, &mut file)?;
+                true
+            }
+            _ => false,
+        };
+        versions.push(Version {
+            number,
+            sha256: sha256.to_string(),
+            archived,
+        });
+        // Drop the oldest versions over the limit
+        let excess = versions.len().saturating_sub(self.max_versions.max(1));
+        for version in versions.drain(..excess) {
+            if let Some(directory) = self.directory.as_ref().filter(|_| version.archived) {
+                let _ = fs::remove_file(directory.join(format!("{id}.{}", version.number)));
+            }
+        }
+        self.write_index()?;
+        Ok(number)
+    }
+
+    /// Returns the number of the latest version of the item
+    pub fn current_version(&self, id: u8) -> Option<u32> {
+        self.versions.get(&id)?.last().map(|version| version.number)
+    }
+
+    /// Returns the numbers of the versions of the item that can still be read, oldest first:
+    /// the archived ones and the latest one
+    pub fn versions(&self, id: u8) -> Vec<u32> {
+        let latest = self.current_version(id);
+        self.versions.get(&id).map_or_else(Vec::new, |versions| {
+            versions
+                .iter()
+                .filter(|version| version.archived || Some(version.number) == latest)
+                .map(|version| version.number)
+                .collect()
+        })
+    }
+
+    /// Returns the bytes of an archived version of the item,
+    /// the latest version is read from the served file instead
+    /// # Errors
+    /// Returns `NotFound` if the version is not archived, or the error raised reading it
+    pub fn get(&self, id: u8, number: u32) -> io::Result<Vec<u8>> {
+        let archived = self
+            .versions
+            .get(&id)
+            .and_then(|versions| versions.iter().find(|version| version.number == number))
+            .is_some_and(|version| version.archived);
+        match &self.directory {
+            Some(directory) if archived => fs::read(directory.join(format!("{id}.{number}"))),
+            _ => Err(not_found(id)),
+        }
+    }
+}
+
+/// Parses the id and the version number from the name of an archived version, e.g. `3.2`
+fn parse_version_name(path: &Path) -> Option<(u8, u32)> {
+    let id = path.file_stem()?.to_str()?.parse().ok()?;
+    let number = path.extension()?.to_str()?.parse().ok()?;
+    Some((id, number))
+}
@@ -1,5 +1,23 @@
+pub mod builder;
+pub mod compression;
+pub mod config;
 #[allow(dead_code)]
 pub mod content_server;
+pub mod content_store;
+pub mod error;
+pub mod history;
+pub mod links;
+pub mod logging;
+pub mod manifest;
+pub mod media;
+pub mod messages;
+pub mod response_cache;
+pub mod search;
+pub mod selection;
+pub mod simulation;
+pub mod stream;
+pub mod text;
+pub mod variants;
 
 #[cfg(test)]
 mod tests {
@@ -0,0 +1,55 @@
+use log::error;
+
+/// Prefix of the references to a media file, both in `[media:12]` and in `![alt](media:12)`
+const MEDIA_PREFIX: &str = "media:";
+
+/// Returns the ids of the media files referenced by a text, in order of first appearance.
+/// References are written `[media:12]` or as Markdown images, `![alt](media:12)` or
+/// `![alt](12.png)`; images with an external URL are ignored and malformed ids are skipped
+pub fn media_references(text: &str) -> Vec<u8> {
+    let mut references = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('[') {
+        let is_image = rest[..start].ends_with('!');
+        rest = &rest[start + 1..];
+        let Some(end) = rest.find(']') else {
+            break;
+        };
+        let label = &rest[..end];
+        let target = if let Some(id) = label.strip_prefix(MEDIA_PREFIX) {
+            // Short form: [media:12]
+            Some(id)
+        } else if is_image && rest[end + 1..].starts_with('(') {
+            // Markdown image: ![alt](target "title")
+            let link = &rest[end + 2..];
+            link.find(')')
+                .and_then(|close| link[..close].split_whitespace().next())
+                .and_then(media_target)
+        } else {
+            None
+        };
+        if let Some(target) = target {
+            match target.trim().parse::<u8>() {
+                Ok(id) if !references.contains(&id) => references.push(id),
+                Ok(_) => {}
+                Err(_) => error!("Warning: Invalid media reference '{target}'\n"),
+            }
+        }
+        rest = &rest[end + 1..];
+    }
+    references
+}
+
+/// Returns the media id of the target of a Markdown image,
+/// without the `media:` prefix or the extension, `None` if it's an external URL
+fn media_target(target: &str) -> Option<&str> {
+    if let Some(id) = target.strip_prefix(MEDIA_PREFIX) {
+        return Some(id);
+    }
+    if target.contains("://") {
+        return None;
+    }
+    // A file of the media server, named after its id
+    let name = target.rsplit('/').next().unwrap_or(target);
+    Some(name.split_once('.').map_or(name, |(stem, _)| stem))
+}
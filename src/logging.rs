@@ -0,0 +1,52 @@
+use rustafarian_shared::logger::LogLevel::{self, DEBUG, ERROR};
+use rustafarian_shared::logger::Logger;
+use serde::{Deserialize, Serialize};
+
+/// Most detailed messages written to the log of a server
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum LogLevelFilter {
+    /// Only the errors
+    Error,
+    /// The errors and the requests served
+    Info,
+    /// Everything, including every packet received
+    #[default]
+    Debug,
+}
+
+impl LogLevelFilter {
+    /// Checks if a message of the level is written
+    pub fn allows(self, level: &LogLevel) -> bool {
+        let filter = if matches!(level, ERROR) {
+            LogLevelFilter::Error
+        } else if matches!(level, DEBUG) {
+            LogLevelFilter::Debug
+        } else {
+            LogLevelFilter::Info
+        };
+        filter <= self
+    }
+}
+
+/// Logger of a server that drops the messages more detailed than its level
+pub struct ServerLogger {
+    logger: Logger,
+    pub level: LogLevelFilter,
+}
+
+impl ServerLogger {
+    /// Creates the logger of a node, nothing is written if `is_debug` is false
+    pub fn new(name: String, id: u8, is_debug: bool) -> Self {
+        ServerLogger {
+            logger: Logger::new(name, id, is_debug),
+            level: LogLevelFilter::default(),
+        }
+    }
+
+    /// Writes the message if its level is allowed
+    pub fn log(&mut self, message: &str, level: LogLevel) {
+        if self.level.allows(&level) {
+            self.logger.log(message, level);
+        }
+    }
+}
@@ -1,3 +1,77 @@
-mod content_server;
+use std::collections::HashMap;
+use std::env;
+use std::path::Path;
+use std::process::ExitCode;
 
-fn main() {}
+use rustafarian_content_server::config::ContentServerConfig;
+use rustafarian_content_server::simulation::{self, Network};
+
+const USAGE: &str =
+    "Usage: rustafarian-content-server <network.toml> [seed=<seed>] [<server id>=<server config>]...";
+
+/// Runs the servers of a `wg_2024` network configuration as content servers,
+/// with stand-in drones and clients, and prints what each client finds on the servers
+fn main() -> ExitCode {
+    env_logger::init();
+    let mut args = env::args().skip(1);
+    let Some(network_path) = args.next() else {
+        eprintln!("{USAGE}");
+        return ExitCode::FAILURE;
+    };
+
+    // Configuration files of the servers, e.g. `3=media_server.toml`,
+    // and the seed of the drones, e.g. `seed=42`
+    let mut server_configs = HashMap::new();
+    let mut seed = None;
+    for arg in args {
+        let Some((id, path)) = arg.split_once('=') else {
+            eprintln!("{USAGE}");
+            return ExitCode::FAILURE;
+        };
+        if id == "seed" {
+            let Ok(value) = path.parse::<u64>() else {
+                eprintln!("Error: '{path}' is not a seed");
+                return ExitCode::FAILURE;
+            };
+            seed = Some(value);
+            continue;
+        }
+        let Ok(id) = id.parse::<u8>() else {
+            eprintln!("Error: '{id}' is not a server id");
+            return ExitCode::FAILURE;
+        };
+        match ContentServerConfig::load(Path::new(path)) {
+            Ok(server_config) => {
+                server_configs.insert(id, server_config);
+            }
+            Err(err) => {
+                eprintln!("Error: {err}");
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    let network = simulation::load_network(Path::new(&network_path))
+        .and_then(|config| Network::start(&config, &server_configs, seed));
+    let mut network = match network {
+        Ok(network) => network,
+        Err(err) => {
+            eprintln!("Error: {err}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let mut visits = network.browse();
+    visits.sort_by_key(|(client_id, _)| *client_id);
+    for (client_id, client_visits) in visits {
+        for visit in client_visits {
+            println!("Client {client_id} found {visit}");
+        }
+    }
+    if let Err(panicked) = network.shutdown() {
+        for server_id in panicked {
+            eprintln!("Error: server {server_id} panicked");
+        }
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,81 @@
+use log::error;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Description of a content item given by the catalog manifest
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub id: u8,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub mime_type: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// File of the item relative to the content directory,
+    /// if missing the file is the one named after the id
+    #[serde(default)]
+    pub path: Option<String>,
+}
+
+/// Optional file in a content directory that describes the items it contains,
+/// it can be written in JSON (`manifest.json`) or TOML (`manifest.toml`)
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Manifest {
+    #[serde(default)]
+    pub entries: Vec<ManifestEntry>,
+}
+
+impl Manifest {
+    /// Names of the manifest files looked up in a content directory, in order
+    pub const FILE_NAMES: [&'static str; 2] = ["manifest.json", "manifest.toml"];
+
+    /// Loads the manifest of the directory, returns `None` if there is no manifest
+    /// or if it can't be parsed
+    pub fn load(directory: &Path) -> Option<Manifest> {
+        let path = Self::FILE_NAMES
+            .iter()
+            .map(|name| directory.join(name))
+            .find(|path| path.exists())?;
+        let raw = match fs::read_to_string(&path) {
+            Ok(raw) => raw,
+            Err(err) => {
+                error!("Error reading manifest '{}': {err}\n", path.display());
+                return None;
+            }
+        };
+        let parsed = if path.extension().is_some_and(|ext| ext == "toml") {
+            toml::from_str(&raw).map_err(|err| err.to_string())
+        } else {
+            serde_json::from_str(&raw).map_err(|err| err.to_string())
+        };
+        match parsed {
+            Ok(manifest) => Some(manifest),
+            Err(err) => {
+                error!("Error parsing manifest '{}': {err}\n", path.display());
+                None
+            }
+        }
+    }
+
+    /// Returns the entry of the item with that id
+    pub fn get(&self, id: u8) -> Option<&ManifestEntry> {
+        self.entries.iter().find(|entry| entry.id == id)
+    }
+
+    /// Returns the files listed with an explicit path, resolved against the directory
+    pub fn sources(&self, directory: &Path) -> Vec<(u8, PathBuf)> {
+        self.entries
+            .iter()
+            .filter_map(|entry| {
+                entry
+                    .path
+                    .as_ref()
+                    .map(|path| (entry.id, directory.join(path)))
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,50 @@
+use image::io::Reader;
+use image::{DynamicImage, ImageFormat, ImageResult};
+use std::io::{BufRead, Cursor, Seek};
+
+/// Returns the file extensions of the image formats that can be decoded
+pub fn readable_extensions() -> Vec<&'static str> {
+    ImageFormat::all()
+        .filter(|format| format.can_read() && format.reading_enabled())
+        .flat_map(|format| format.extensions_str().iter().copied())
+        .collect()
+}
+
+/// Returns the image format with that MIME type or extension, if it can be encoded
+pub fn writable_format(name: &str) -> Option<ImageFormat> {
+    ImageFormat::from_mime_type(name)
+        .or_else(|| ImageFormat::from_extension(name))
+        .filter(|format| format.can_write() && format.writing_enabled())
+}
+
+/// Decodes an image, returning it together with its original format
+/// # Errors
+/// Returns an error if the format is unknown or the image can't be decoded
+pub fn decode(data: &[u8]) -> ImageResult<(DynamicImage, ImageFormat)> {
+    let format = image::guess_format(data)?;
+    let image = image::load_from_memory_with_format(data, format)?;
+    Ok((image, format))
+}
+
+/// Returns the width and height of an image, reading only its header
+/// # Errors
+/// Returns an error if the format is unknown or the header can't be read
+pub fn dimensions(data: &[u8]) -> ImageResult<(u32, u32)> {
+    read_dimensions(Cursor::new(data))
+}
+
+/// Returns the width and height of the image read from the reader, like `dimensions`
+/// # Errors
+/// Returns an error if the format is unknown or the header can't be read
+pub fn read_dimensions(reader: impl BufRead + Seek) -> ImageResult<(u32, u32)> {
+    Reader::new(reader).with_guessed_format()?.into_dimensions()
+}
+
+/// Encodes an image in the given format
+/// # Errors
+/// Returns an error if the image can't be encoded in that format
+pub fn encode(image: &DynamicImage, format: ImageFormat) -> ImageResult<Vec<u8>> {
+    let mut buffer = Vec::new();
+    image.write_to(&mut Cursor::new(&mut buffer), format)?;
+    Ok(buffer)
+}
@@ -0,0 +1,372 @@
+use crate::compression::ContentEncoding;
+use crate::search::SearchHit;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io;
+
+/// Requests understood by the content server in addition to the shared `BrowserRequestWrapper` ones.
+/// The `kind` of a request picks the text or the media files of a hybrid server,
+/// the files of the server type are used when it's `None`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ContentRequest {
+    /// Asks for the list of the files with their catalog details
+    FileCatalog {
+        #[serde(default)]
+        kind: Option<ContentKind>,
+    },
+    /// Asks for a media file encoded in the given format (a MIME type or an extension),
+    /// or in its original format if there is none
+    MediaFile { id: u8, format: Option<String> },
+    /// Asks for a thumbnail of a media file, with the size and quality configured on the server
+    Thumbnail(u8),
+    /// Asks for a media file resized to fit `max_dimension` and encoded as JPEG with that quality
+    MediaVariant {
+        id: u8,
+        max_dimension: u32,
+        quality: u8,
+    },
+    /// Asks for the ids of the media files referenced by a text file
+    MediaReferences(u8),
+    /// Publishes a file on the server, UTF-8 text on a text server or an image on a media server,
+    /// only files of the server type can be uploaded
+    Upload {
+        data: Vec<u8>,
+        #[serde(default)]
+        kind: Option<ContentKind>,
+    },
+    /// Asks for the list of the files with their current version,
+    /// only the files of the server type have versions
+    VersionedFileList {
+        #[serde(default)]
+        kind: Option<ContentKind>,
+    },
+    /// Asks for a version of a file, the latest one if `version` is `None`
+    FileVersion {
+        id: u8,
+        version: Option<u32>,
+        #[serde(default)]
+        kind: Option<ContentKind>,
+    },
+    /// Searches the words of the query in the text files
+    Search { query: String },
+    /// Asks for the details of a file
+    Metadata {
+        id: u8,
+        #[serde(default)]
+        kind: Option<ContentKind>,
+    },
+    /// Asks for the list of the files with their details
+    DetailedFileList {
+        #[serde(default)]
+        kind: Option<ContentKind>,
+    },
+    /// Asks for `length` bytes of a file starting at `offset`, or all the bytes
+    /// from `offset` to the end if `length` is `None`
+    Range {
+        id: u8,
+        offset: u64,
+        length: Option<u64>,
+        #[serde(default)]
+        kind: Option<ContentKind>,
+    },
+    /// Asks for the kinds of content served, both kinds on a hybrid server
+    Capabilities,
+    /// Asks for the ids of the files of every kind served, each one tagged with its kind
+    TaggedFileList,
+}
+
+/// Responses to a `ContentRequest`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ContentResponse {
+    FileCatalog(Vec<CatalogEntry>),
+    /// `sha256` is the digest of `data` when the server sent the stored bytes untouched
+    MediaFile {
+        id: u8,
+        mime_type: String,
+        sha256: Option<String>,
+        data: Vec<u8>,
+    },
+    /// A media file whose bytes are encoded in base64, sent instead of a `MediaFile`
+    /// when the media encoding is `Base64`, the bytes are read with `decode_media`
+    CompactMediaFile {
+        id: u8,
+        mime_type: String,
+        sha256: Option<String>,
+        data: String,
+    },
+    /// A JPEG rendition of a media file
+    MediaVariant {
+        id: u8,
+        width: u32,
+        height: u32,
+        data: Vec<u8>,
+    },
+    /// Ids of the media files referenced by a text file, to be fetched from a media server
+    MediaReferences {
+        id: u8,
+        media: Vec<u8>,
+    },
+    /// The upload was stored with that id
+    Uploaded(u8),
+    /// The upload was refused, with the reason
+    UploadRejected(String),
+    VersionedFileList(Vec<VersionedFile>),
+    /// The stored bytes of a version of a file
+    FileVersion {
+        id: u8,
+        version: u32,
+        data: Vec<u8>,
+    },
+    /// The files matching the query, best first
+    SearchResults {
+        query: String,
+        hits: Vec<SearchHit>,
+    },
+    Metadata(FileDetails),
+    DetailedFileList(Vec<FileDetails>),
+    /// A slice of the stored bytes of a file, `total_size` is the size of the whole file
+    Range {
+        id: u8,
+        offset: u64,
+        total_size: u64,
+        data: Vec<u8>,
+    },
+    /// The request couldn't be answered
+    Error(ContentError),
+    /// Kinds of content served, sent in response to a `Capabilities` request
+    ServerCapabilities(Vec<ContentKind>),
+    /// Files of every kind served, each one tagged with its kind
+    TaggedFileList(Vec<TaggedFile>),
+}
+
+/// Kind of content served by a server
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ContentKind {
+    Text,
+    Media,
+}
+
+/// A file of a hybrid server, text and media files can have the same id
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TaggedFile {
+    pub kind: ContentKind,
+    pub id: u8,
+}
+
+/// Requests understood by a chat server
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ChatRequest {
+    /// Registers the sender as a client of the chat
+    Register,
+    /// Asks for the ids of the registered clients
+    ClientList,
+    /// Relays a message to another registered client
+    SendMessage { to: u8, message: String },
+}
+
+/// Responses to a `ChatRequest`, and the messages relayed to the clients
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ChatResponse {
+    /// The sender is registered
+    Registered,
+    ClientList(Vec<u8>),
+    /// The message was relayed to the client
+    MessageSent {
+        to: u8,
+    },
+    /// A message sent by another client
+    MessageFrom {
+        from: u8,
+        message: String,
+    },
+}
+
+/// Why a request couldn't be answered
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ContentError {
+    /// No file, or version of a file, with that id is served
+    NotFound(u8),
+    /// The request can't be handled by this type of server
+    WrongServerType(String),
+    /// The request can't be deserialized or its arguments are invalid
+    BadRequest(String),
+    /// The server failed reading or encoding the content
+    InternalError(String),
+    /// The server gave up sending the response of the session with this id,
+    /// this error is sent under a new session
+    SessionAborted(u64),
+}
+
+impl ContentError {
+    /// Returns the error to send when reading the file with that id failed
+    pub fn reading(id: u8, err: &io::Error) -> Self {
+        match err.kind() {
+            io::ErrorKind::NotFound => ContentError::NotFound(id),
+            io::ErrorKind::InvalidInput => ContentError::BadRequest(err.to_string()),
+            _ => ContentError::InternalError(format!("Error reading file '{id}': {err}")),
+        }
+    }
+}
+
+impl fmt::Display for ContentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ContentError::NotFound(id) => write!(f, "File with ID '{id}' not found"),
+            ContentError::SessionAborted(session_id) => {
+                write!(f, "The response of session {session_id} was given up")
+            }
+            ContentError::WrongServerType(message)
+            | ContentError::BadRequest(message)
+            | ContentError::InternalError(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+/// How the bytes of a media file are written in the JSON of its response
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MediaEncoding {
+    /// An array of numbers, in a `MediaFile` response
+    #[default]
+    Array,
+    /// A base64 string, a third bigger than the bytes, in a `CompactMediaFile` response
+    Base64,
+}
+
+/// Encodes the bytes of a media file in base64
+pub fn encode_media(data: &[u8]) -> String {
+    STANDARD.encode(data)
+}
+
+/// Decodes the bytes of a media file sent in a `CompactMediaFile` response
+/// # Errors
+/// Returns the error raised if the data is not valid base64
+pub fn decode_media(data: &str) -> Result<Vec<u8>, base64::DecodeError> {
+    STANDARD.decode(data)
+}
+
+/// What a client needs to know about a file before downloading it
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileDetails {
+    pub id: u8,
+    /// Size in bytes of the stored file
+    pub size: u64,
+    pub mime_type: Option<String>,
+    /// Width and height in pixels of a media file
+    pub dimensions: Option<(u32, u32)>,
+    /// Last-modified time in RFC 3339 format
+    pub modified: Option<String>,
+    /// Number of fragments of the response to a `TextFileRequest` or `MediaFileRequest`
+    pub fragments: u64,
+}
+
+/// A file of the server with its versions, numbered from 1
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VersionedFile {
+    pub id: u8,
+    /// Number of the version currently served
+    pub version: u32,
+    /// Numbers of the versions that can still be requested, oldest first
+    pub versions: Vec<u32>,
+}
+
+/// A file of the server as described by the catalog manifest
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CatalogEntry {
+    pub id: u8,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub mime_type: Option<String>,
+    pub tags: Vec<String>,
+    /// Hex SHA-256 digest of the stored bytes
+    pub sha256: Option<String>,
+}
+
+/// Content added, modified or removed since the last scan of the content directory
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContentChanges {
+    pub added: Vec<u8>,
+    pub modified: Vec<u8>,
+    pub removed: Vec<u8>,
+}
+
+impl ContentChanges {
+    /// Checks if nothing changed
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.modified.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Events sent by the content server to the simulation controller
+/// in addition to the shared `SimControllerResponseWrapper` ones
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ContentEvent {
+    /// The content served by the server changed after a rescan
+    ContentChanged {
+        server_id: u8,
+        changes: ContentChanges,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ContentRequestWrapper {
+    Content(ContentRequest),
+    Chat(ChatRequest),
+    /// Wraps a serialized request, of any kind, whose response the client accepts compressed
+    /// with one of the `encodings`, the server uses the first one it supports
+    AcceptEncoding {
+        encodings: Vec<ContentEncoding>,
+        request: String,
+    },
+    /// Wraps a serialized request, of any kind, whose media response is written with `encoding`
+    /// instead of the one configured on the server
+    AcceptMediaEncoding {
+        encoding: MediaEncoding,
+        request: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ContentResponseWrapper {
+    Content(ContentResponse),
+    Chat(ChatResponse),
+    /// A serialized response compressed with `encoding` and encoded in base64,
+    /// it's read with `compression::decode_message`
+    Encoded {
+        encoding: ContentEncoding,
+        data: String,
+    },
+}
+
+impl ContentRequestWrapper {
+    /// Serializes the request to JSON
+    /// # Panics
+    /// Panics if the request can't be serialized
+    pub fn stringify(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+    /// Deserializes a request from JSON
+    /// # Errors
+    /// Returns the error raised by the deserialization
+    pub fn from_string(raw: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(raw)
+    }
+}
+
+impl ContentResponseWrapper {
+    /// Serializes the response to JSON
+    /// # Panics
+    /// Panics if the response can't be serialized
+    pub fn stringify(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+    /// Deserializes a response from JSON
+    /// # Errors
+    /// Returns the error raised by the deserialization
+    pub fn from_string(raw: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(raw)
+    }
+}
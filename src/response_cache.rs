@@ -0,0 +1,159 @@
+use std::collections::HashMap;
+
+use crate::variants::VariantKey;
+
+/// Identifies a serialized response kept in the cache
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ResponseKey {
+    TextFile(u8),
+    MediaFile(u8),
+    /// A media file encoded in base64
+    CompactMedia(u8),
+    /// A resized rendition of a media file
+    MediaVariant(VariantKey),
+}
+
+impl ResponseKey {
+    /// Returns the id of the content the response is made of
+    pub fn id(&self) -> u8 {
+        match self {
+            ResponseKey::TextFile(id)
+            | ResponseKey::MediaFile(id)
+            | ResponseKey::CompactMedia(id) => *id,
+            ResponseKey::MediaVariant(key) => key.id,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct CachedResponse {
+    response: String,
+    last_used: u64,
+}
+
+/// Least recently used cache of serialized responses, bounded by their total size in bytes
+#[derive(Debug, Default)]
+pub struct ResponseCache {
+    capacity: usize,
+    size: usize,
+    clock: u64,
+    responses: HashMap<ResponseKey, CachedResponse>,
+    hits: u64,
+    misses: u64,
+}
+
+impl ResponseCache {
+    /// Creates a cache holding at most `capacity` bytes of responses, 0 disables it
+    pub fn new(capacity: usize) -> Self {
+        ResponseCache {
+            capacity,
+            ..ResponseCache::default()
+        }
+    }
+
+    /// Returns the response with that key and marks it as recently used,
+    /// counting a hit or a miss
+    pub fn get(&mut self, key: &ResponseKey) -> Option<String> {
+        self.clock += 1;
+        match self.responses.get_mut(key) {
+            Some(cached) => {
+                cached.last_used = self.clock;
+                self.hits += 1;
+                Some(cached.response.clone())
+            }
+            None => {
+                self.misses += 1;
+                None
+            }
+        }
+    }
+
+    /// Returns the size of the response with that key, without counting a hit or a miss
+    pub fn peek_len(&self, key: &ResponseKey) -> Option<usize> {
+        self.responses.get(key).map(|cached| cached.response.len())
+    }
+
+    /// Stores a response, evicting the least recently used ones until it fits,
+    /// responses bigger than the whole cache are not stored
+    pub fn insert(&mut self, key: ResponseKey, response: String) {
+        if response.len() > self.capacity {
+            return;
+        }
+        self.remove(&key);
+        while self.size + response.len() > self.capacity {
+            let Some(oldest) = self
+                .responses
+                .iter()
+                .min_by_key(|(_, cached)| cached.last_used)
+                .map(|(key, _)| *key)
+            else {
+                break;
+            };
+            self.remove(&oldest);
+        }
+        self.clock += 1;
+        self.size += response.len();
+        self.responses.insert(
+            key,
+            CachedResponse {
+                response,
+                last_used: self.clock,
+            },
+        );
+    }
+
+    /// Removes all the responses made of the content with that id, used when it changes
+    pub fn invalidate(&mut self, id: u8) {
+        let keys: Vec<ResponseKey> = self
+            .responses
+            .keys()
+            .filter(|key| key.id() == id)
+            .copied()
+            .collect();
+        for key in keys {
+            self.remove(&key);
+        }
+    }
+
+    /// Removes all the responses
+    pub fn clear(&mut self) {
+        self.responses.clear();
+        self.size = 0;
+    }
+
+    fn remove(&mut self, key: &ResponseKey) {
+        if let Some(cached) = self.responses.remove(key) {
+            self.size -= cached.response.len();
+        }
+    }
+
+    /// Returns the number of lookups that found a response
+    pub fn hits(&self) -> u64 {
+        self.hits
+    }
+
+    /// Returns the number of lookups that found nothing
+    pub fn misses(&self) -> u64 {
+        self.misses
+    }
+
+    /// Returns the total size in bytes of the cached responses
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Returns the maximum total size in bytes of the cached responses
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the number of cached responses
+    pub fn len(&self) -> usize {
+        self.responses.len()
+    }
+
+    /// Checks if there are no cached responses
+    pub fn is_empty(&self) -> bool {
+        self.responses.is_empty()
+    }
+}
@@ -0,0 +1,142 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io;
+
+/// Number of characters shown around the first match in a snippet
+const SNIPPET_CONTEXT: usize = 40;
+
+/// A text file matching a search, with an excerpt around the first match
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SearchHit {
+    pub id: u8,
+    pub snippet: String,
+}
+
+/// Inverted index of the words of the text files, used to answer search requests
+#[derive(Debug, Default)]
+pub struct SearchIndex {
+    /// Number of occurrences of each word in each file
+    postings: HashMap<String, HashMap<u8, u32>>,
+    /// Text of each file, used for the snippets, `None` for the files indexed without their text
+    documents: HashMap<u8, Option<String>>,
+}
+
+impl SearchIndex {
+    /// Adds a file to the index, replacing the previous text with the same id
+    pub fn insert(&mut self, id: u8, text: String) {
+        self.remove(id);
+        add_words(&mut self.postings, id, &text);
+        self.documents.insert(id, Some(text));
+    }
+
+    /// Adds a file to the index like `insert` without keeping its text, which `read` passes
+    /// a few lines at a time: the hits in the file have no snippet
+    /// # Errors
+    /// Returns the error raised by `read`, the file is then left out of the index
+    pub fn insert_lines(
+        &mut self,
+        id: u8,
+        read: impl FnOnce(&mut dyn FnMut(&str)) -> io::Result<()>,
+    ) -> io::Result<()> {
+        self.remove(id);
+        self.documents.insert(id, None);
+        let postings = &mut self.postings;
+        let result = read(&mut |lines| add_words(postings, id, lines));
+        if result.is_err() {
+            self.remove(id);
+        }
+        result
+    }
+
+    /// Removes a file from the index
+    pub fn remove(&mut self, id: u8) {
+        if self.documents.remove(&id).is_none() {
+            return;
+        }
+        self.postings.retain(|_, files| {
+            files.remove(&id);
+            !files.is_empty()
+        });
+    }
+
+    /// Returns at most `limit` files containing at least one word of the query,
+    /// ranked by TF-IDF, the ones with the same score ordered by id
+    pub fn search(&self, query: &str, limit: usize) -> Vec<SearchHit> {
+        let mut terms: Vec<String> = words(query).into_iter().map(|(word, _)| word).collect();
+        terms.sort_unstable();
+        terms.dedup();
+
+        let total = self.documents.len() as f64;
+        let mut scores: HashMap<u8, f64> = HashMap::new();
+        for term in &terms {
+            let Some(files) = self.postings.get(term) else {
+                continue;
+            };
+            // Rare words weigh more than common ones
+            let idf = (1.0 + total / files.len() as f64).ln();
+            for (id, count) in files {
+                *scores.entry(*id).or_insert(0.0) += f64::from(*count) * idf;
+            }
+        }
+
+        let mut ranked: Vec<(u8, f64)> = scores.into_iter().collect();
+        ranked.sort_by(|(a_id, a), (b_id, b)| b.total_cmp(a).then(a_id.cmp(b_id)));
+        ranked
+            .into_iter()
+            .take(limit)
+            .map(|(id, _)| SearchHit {
+                id,
+                snippet: self.snippet(id, &terms),
+            })
+            .collect()
+    }
+
+    /// Returns the text around the first match of a term in the file
+    fn snippet(&self, id: u8, terms: &[String]) -> String {
+        let Some(Some(text)) = self.documents.get(&id) else {
+            return String::new();
+        };
+        let start = words(text)
+            .into_iter()
+            .find(|(word, _)| terms.contains(word))
+            .map_or(0, |(_, start)| start);
+        let before: Vec<char> = text[..start].chars().rev().take(SNIPPET_CONTEXT).collect();
+        let after: String = text[start..].chars().take(SNIPPET_CONTEXT * 2).collect();
+
+        let mut snippet = String::new();
+        if text[..start].chars().count() > SNIPPET_CONTEXT {
+            snippet.push('…');
+        }
+        snippet.extend(before.iter().rev());
+        snippet.push_str(&after);
+        if start + after.len() < text.len() {
+            snippet.push('…');
+        }
+        // Keep the snippet on a single line
+        snippet.split_whitespace().collect::<Vec<_>>().join(" ")
+    }
+}
+
+/// Counts the occurrences of the words of a text in a file
+fn add_words(postings: &mut HashMap<String, HashMap<u8, u32>>, id: u8, text: &str) {
+    for (word, _) in words(text) {
+        *postings.entry(word).or_default().entry(id).or_insert(0) += 1;
+    }
+}
+
+/// Splits a text into lowercase words, returning each one with its byte offset
+fn words(text: &str) -> Vec<(String, usize)> {
+    let mut words = Vec::new();
+    let mut start = None;
+    for (index, character) in text.char_indices().chain([(text.len(), ' ')]) {
+        match (character.is_alphanumeric(), start) {
+            (true, None) => start = Some(index),
+            (false, Some(word_start)) => {
+                words.push((text[word_start..index].to_lowercase(), word_start));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    words
+}
@@ -0,0 +1,127 @@
+use glob::Pattern;
+use log::error;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use crate::error::ContentServerError;
+
+/// Decides which of the files found in a content directory are served by the server
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SelectionPolicy {
+    /// Serve every file
+    All,
+    /// Serve `n` files picked at random
+    Random(usize),
+    /// Serve the first `n` files ordered by id
+    FirstSorted(usize),
+    /// Serve only the files with these ids
+    Ids(Vec<u8>),
+    /// Serve the files whose name matches at least one `include` pattern and no `exclude` pattern,
+    /// an empty `include` list matches every file
+    Glob {
+        include: Vec<String>,
+        exclude: Vec<String>,
+    },
+}
+
+impl SelectionPolicy {
+    /// Checks that every glob pattern of the policy can be compiled
+    /// # Errors
+    /// Returns `InvalidConfig` naming the first invalid pattern
+    pub fn validate(&self) -> Result<(), ContentServerError> {
+        if let SelectionPolicy::Glob { include, exclude } = self {
+            for pattern in include.iter().chain(exclude) {
+                Pattern::new(pattern).map_err(|err| {
+                    ContentServerError::InvalidConfig(format!(
+                        "Invalid glob pattern '{pattern}': {err}"
+                    ))
+                })?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the candidates selected by the policy, ordered by id,
+    /// `rng` is used by the random policy so the same seed always selects the same files
+    pub fn apply<R: Rng + ?Sized>(
+        &self,
+        mut candidates: Vec<(u8, PathBuf)>,
+        rng: &mut R,
+    ) -> Vec<(u8, PathBuf)> {
+        candidates.sort_by_key(|(id, _)| *id);
+        match self {
+            SelectionPolicy::All => candidates,
+            SelectionPolicy::Random(n) => {
+                candidates.shuffle(rng);
+                candidates.truncate(*n);
+                candidates.sort_by_key(|(id, _)| *id);
+                candidates
+            }
+            SelectionPolicy::FirstSorted(n) => {
+                candidates.truncate(*n);
+                candidates
+            }
+            SelectionPolicy::Ids(ids) => candidates
+                .into_iter()
+                .filter(|(id, _)| ids.contains(id))
+                .collect(),
+            SelectionPolicy::Glob { include, exclude } => {
+                // An invalid pattern selects nothing rather than more than was asked
+                let (Some(include), Some(exclude)) =
+                    (compile_patterns(include), compile_patterns(exclude))
+                else {
+                    return Vec::new();
+                };
+                candidates
+                    .into_iter()
+                    .filter(|(_, path)| {
+                        let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+                            return false;
+                        };
+                        (include.is_empty() || include.iter().any(|p| p.matches(name)))
+                            && !exclude.iter().any(|p| p.matches(name))
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    /// Returns the candidates selected by the policy when the directory is scanned again:
+    /// the random policy keeps the `current` files that still exist and picks new ones
+    /// only to replace the missing ones, the other policies select as `apply` does
+    pub fn reapply<R: Rng + ?Sized>(
+        &self,
+        candidates: Vec<(u8, PathBuf)>,
+        current: &[u8],
+        rng: &mut R,
+    ) -> Vec<(u8, PathBuf)> {
+        let SelectionPolicy::Random(n) = self else {
+            return self.apply(candidates, rng);
+        };
+        let (mut kept, others): (Vec<_>, Vec<_>) = candidates
+            .into_iter()
+            .partition(|(id, _)| current.contains(id));
+        kept.sort_by_key(|(id, _)| *id);
+        kept.truncate(*n);
+        let missing = n - kept.len();
+        kept.extend(SelectionPolicy::Random(missing).apply(others, rng));
+        kept.sort_by_key(|(id, _)| *id);
+        kept
+    }
+}
+
+/// Compiles the glob patterns, returns `None` if one of them is invalid
+fn compile_patterns(patterns: &[String]) -> Option<Vec<Pattern>> {
+    patterns
+        .iter()
+        .map(|pattern| match Pattern::new(pattern) {
+            Ok(pattern) => Some(pattern),
+            Err(err) => {
+                error!("Error: Invalid glob pattern '{pattern}': {err}\n");
+                None
+            }
+        })
+        .collect()
+}
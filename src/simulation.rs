@@ -0,0 +1,692 @@
+use crossbeam_channel::{select_biased, unbounded, Receiver, Sender};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rustafarian_shared::assembler::{assembler::Assembler, disassembler::Disassembler};
+use rustafarian_shared::messages::browser_messages::{
+    BrowserRequest, BrowserRequestWrapper, BrowserResponse, BrowserResponseWrapper,
+};
+use rustafarian_shared::messages::commander_messages::{
+    SimControllerCommand, SimControllerResponseWrapper,
+};
+use rustafarian_shared::messages::general_messages::{
+    DroneSend, ServerType, ServerTypeRequest, ServerTypeResponse,
+};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use wg_2024::config::Config;
+use wg_2024::network::{NodeId, SourceRoutingHeader};
+use wg_2024::packet::{FloodRequest, Nack, NackType, NodeType, Packet, PacketType};
+
+use crate::builder::ContentServerBuilder;
+use crate::config::ContentServerConfig;
+use crate::error::ContentServerError;
+use crate::messages::{
+    self, ChatRequest, ChatResponse, ContentEvent, ContentKind, ContentRequest,
+    ContentRequestWrapper, ContentResponse, ContentResponseWrapper,
+};
+
+/// Reads a network configuration in the TOML format of `wg_2024`
+/// # Errors
+/// Returns `InvalidConfig` if the file can't be read or parsed
+pub fn load_network(path: &Path) -> Result<Config, ContentServerError> {
+    let raw = fs::read_to_string(path).map_err(|err| {
+        ContentServerError::InvalidConfig(format!(
+            "Error reading network '{}': {err}",
+            path.display()
+        ))
+    })?;
+    toml::from_str(&raw).map_err(|err| ContentServerError::InvalidConfig(err.to_string()))
+}
+
+/// A network where the servers of the configuration are content servers,
+/// and the drones and clients are stand-in nodes, each one running in its own thread
+pub struct Network {
+    /// Controller channel and thread of each server
+    servers: Vec<(NodeId, Sender<SimControllerCommand>, JoinHandle<()>)>,
+    pub clients: Vec<StandInClient>,
+    drones: Vec<JoinHandle<()>>,
+    /// Dropped to stop the drones
+    stop: Sender<()>,
+    /// Keeps the channel where the servers answer the controller open
+    controller_responses: Receiver<SimControllerResponseWrapper>,
+    /// Changes of the content of the servers, found by their rescans
+    pub content_events: Receiver<ContentEvent>,
+}
+
+impl Network {
+    /// Creates the nodes of the configuration and starts the servers and the drones,
+    /// a server uses its configuration in `server_configs` if any, otherwise the servers
+    /// without one serve text and media files in turn from the default directories.
+    /// Each drone drops fragments with a generator seeded from `seed` and its id,
+    /// the same seed drops the same fragments, if missing the generators are seeded from entropy
+    /// # Errors
+    /// Returns `InvalidConfig` if a node is connected to an unknown node,
+    /// or the error raised creating a server, in which case nothing is started
+    pub fn start(
+        config: &Config,
+        server_configs: &HashMap<NodeId, ContentServerConfig>,
+        seed: Option<u64>,
+    ) -> Result<Network, ContentServerError> {
+        let mut channels: HashMap<NodeId, (Sender<Packet>, Receiver<Packet>)> = HashMap::new();
+        let ids = config
+            .drone
+            .iter()
+            .map(|drone| drone.id)
+            .chain(config.client.iter().map(|client| client.id))
+            .chain(config.server.iter().map(|server| server.id));
+        for id in ids {
+            channels.insert(id, unbounded());
+        }
+        let neighbors = |id: NodeId, connected: &[NodeId]| {
+            connected
+                .iter()
+                .map(|neighbor| match channels.get(neighbor) {
+                    Some((sender, _)) => Ok((*neighbor, sender.clone())),
+                    None => Err(ContentServerError::InvalidConfig(format!(
+                        "Node {id} is connected to unknown node {neighbor}"
+                    ))),
+                })
+                .collect::<Result<HashMap<NodeId, Sender<Packet>>, ContentServerError>>()
+        };
+
+        // Create every server before starting anything
+        let (controller_sender, controller_responses) = unbounded();
+        let (content_sender, content_events) = unbounded();
+        let mut servers = Vec::new();
+        for (index, server) in config.server.iter().enumerate() {
+            let server_config = match server_configs.get(&server.id) {
+                Some(server_config) => ContentServerConfig {
+                    server_id: server.id,
+                    ..server_config.clone()
+                },
+                None => ContentServerConfig {
+                    server_id: server.id,
+                    server_type: if index % 2 == 0 {
+                        ServerType::Text
+                    } else {
+                        ServerType::Media
+                    },
+                    ..ContentServerConfig::default()
+                },
+            };
+            let (commands, command_receiver) = unbounded();
+            let content_server = ContentServerBuilder::from_config(server_config)
+                .senders(neighbors(server.id, &server.connected_drone_ids)?)
+                .receiver(channels[&server.id].1.clone())
+                .controller(command_receiver, controller_sender.clone())
+                .content_listener(content_sender.clone())
+                .build()?;
+            servers.push((server.id, commands, content_server));
+        }
+        let mut drones = Vec::new();
+        for drone in &config.drone {
+            drones.push(StandInDrone::new(
+                drone.id,
+                channels[&drone.id].1.clone(),
+                neighbors(drone.id, &drone.connected_node_ids)?,
+                drone.pdr,
+                seed,
+            ));
+        }
+        let mut clients = Vec::new();
+        for client in &config.client {
+            let mut stand_in = StandInClient::new(
+                client.id,
+                channels[&client.id].1.clone(),
+                neighbors(client.id, &client.connected_drone_ids)?,
+            );
+            stand_in.expected_servers = config.server.iter().map(|server| server.id).collect();
+            clients.push(stand_in);
+        }
+
+        let servers = servers
+            .into_iter()
+            .map(|(id, commands, mut content_server)| {
+                (id, commands, thread::spawn(move || content_server.run()))
+            })
+            .collect();
+        let (stop, stop_receiver) = unbounded();
+        let drones = drones
+            .into_iter()
+            .map(|mut drone| {
+                let stop_receiver = stop_receiver.clone();
+                thread::spawn(move || drone.run(&stop_receiver))
+            })
+            .collect();
+        Ok(Network {
+            servers,
+            clients,
+            drones,
+            stop,
+            controller_responses,
+            content_events,
+        })
+    }
+
+    /// Lets every client browse the servers at the same time, returns what each client found
+    pub fn browse(&mut self) -> Vec<(NodeId, Vec<ServerVisit>)> {
+        thread::scope(|scope| {
+            let handles: Vec<_> = self
+                .clients
+                .iter_mut()
+                .map(|client| scope.spawn(move || (client.id, client.browse())))
+                .collect();
+            handles
+                .into_iter()
+                .filter_map(|handle| handle.join().ok())
+                .collect()
+        })
+    }
+
+    /// Shuts the servers down and stops the drones, waiting for their threads
+    /// # Errors
+    /// Returns the ids of the servers whose thread panicked, every node is stopped anyway
+    pub fn shutdown(self) -> Result<(), Vec<NodeId>> {
+        for (_, commands, _) in &self.servers {
+            let _ = commands.send(SimControllerCommand::Shutdown);
+        }
+        let panicked: Vec<NodeId> = self
+            .servers
+            .into_iter()
+            .filter_map(|(id, _, handle)| handle.join().is_err().then_some(id))
+            .collect();
+        drop(self.stop);
+        for handle in self.drones {
+            let _ = handle.join();
+        }
+        drop(self.controller_responses);
+        if panicked.is_empty() {
+            Ok(())
+        } else {
+            Err(panicked)
+        }
+    }
+}
+
+/// A drone that forwards the packets along their route and drops fragments with probability `pdr`,
+/// answering with a NACK as a real drone does
+pub struct StandInDrone {
+    id: NodeId,
+    receiver: Receiver<Packet>,
+    senders: HashMap<NodeId, Sender<Packet>>,
+    pdr: f32,
+    /// Flood requests already forwarded, by flood id and initiator
+    seen_floods: HashSet<(u64, NodeId)>,
+    /// Decides which fragments are dropped
+    rng: StdRng,
+}
+
+impl StandInDrone {
+    /// Creates a drone whose generator is seeded from `seed` and its id, or from entropy
+    pub fn new(
+        id: NodeId,
+        receiver: Receiver<Packet>,
+        senders: HashMap<NodeId, Sender<Packet>>,
+        pdr: f32,
+        seed: Option<u64>,
+    ) -> Self {
+        StandInDrone {
+            id,
+            receiver,
+            senders,
+            pdr,
+            seen_floods: HashSet::new(),
+            rng: seed.map_or_else(StdRng::from_entropy, |seed| {
+                StdRng::seed_from_u64(seed ^ u64::from(id))
+            }),
+        }
+    }
+
+    /// Forwards the packets until `stop` is closed
+    pub fn run(&mut self, stop: &Receiver<()>) {
+        loop {
+            select_biased! {
+                recv(stop) -> _ => return,
+                recv(self.receiver) -> packet => match packet {
+                    Ok(packet) => self.handle_packet(packet),
+                    Err(_) => return,
+                },
+            }
+        }
+    }
+
+    /// Sends the packet to the next hop of its route
+    pub fn handle_packet(&mut self, mut packet: Packet) {
+        if let PacketType::FloodRequest(flood_request) = &packet.pack_type {
+            let flood_request = flood_request.clone();
+            self.handle_flood_request(flood_request, packet.session_id);
+            return;
+        }
+        let position = packet.routing_header.hop_index;
+        if packet.routing_header.current_hop() != Some(self.id) {
+            self.send_nack(&packet, position, NackType::UnexpectedRecipient(self.id));
+            return;
+        }
+        packet.routing_header.increase_hop_index();
+        let Some(next_hop) = packet.routing_header.current_hop() else {
+            self.send_nack(&packet, position, NackType::DestinationIsDrone);
+            return;
+        };
+        let Some(sender) = self.senders.get(&next_hop) else {
+            self.send_nack(&packet, position, NackType::ErrorInRouting(next_hop));
+            return;
+        };
+        if matches!(packet.pack_type, PacketType::MsgFragment(_))
+            && self.rng.gen::<f32>() < self.pdr
+        {
+            self.send_nack(&packet, position, NackType::Dropped);
+            return;
+        }
+        let _ = sender.send(packet);
+    }
+
+    /// Forwards the flood request to the other neighbors, or answers it if it was already seen
+    /// or there is no one else to forward it to
+    fn handle_flood_request(&mut self, mut flood_request: FloodRequest, session_id: u64) {
+        let previous = flood_request.path_trace.last().map(|(id, _)| *id);
+        flood_request.increment(self.id, NodeType::Drone);
+        let first_visit = self
+            .seen_floods
+            .insert((flood_request.flood_id, flood_request.initiator_id));
+        let targets: Vec<&Sender<Packet>> = self
+            .senders
+            .iter()
+            .filter(|(id, _)| Some(**id) != previous)
+            .map(|(_, sender)| sender)
+            .collect();
+        if first_visit && !targets.is_empty() {
+            for sender in targets {
+                let _ = sender.send(Packet::new_flood_request(
+                    SourceRoutingHeader::empty_route(),
+                    session_id,
+                    flood_request.clone(),
+                ));
+            }
+        } else {
+            let response = flood_request.generate_response(session_id);
+            if let Some(sender) = response
+                .routing_header
+                .current_hop()
+                .and_then(|hop| self.senders.get(&hop))
+            {
+                let _ = sender.send(response);
+            }
+        }
+    }
+
+    /// Sends a NACK back to the source of a fragment, the other packets are only dropped
+    fn send_nack(&self, packet: &Packet, position: usize, nack_type: NackType) {
+        if !matches!(packet.pack_type, PacketType::MsgFragment(_)) {
+            return;
+        }
+        let mut hops: Vec<NodeId> = packet.routing_header.hops
+            [..position.min(packet.routing_header.hops.len())]
+            .iter()
+            .rev()
+            .copied()
+            .collect();
+        hops.insert(0, self.id);
+        let nack = Packet::new_nack(
+            SourceRoutingHeader::new(hops, 1),
+            packet.session_id,
+            Nack {
+                fragment_index: packet.get_fragment_index(),
+                nack_type,
+            },
+        );
+        if let Some(sender) = nack
+            .routing_header
+            .current_hop()
+            .and_then(|hop| self.senders.get(&hop))
+        {
+            let _ = sender.send(nack);
+        }
+    }
+}
+
+/// What a client found on a server
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ServerVisit {
+    pub server_id: NodeId,
+    /// Kinds of content served, empty for a chat server or a server that didn't answer
+    pub kinds: Vec<ContentKind>,
+    pub is_chat: bool,
+    /// Files listed by the server
+    pub files: Vec<u8>,
+    /// Id and size in bytes of the first file, if it was received
+    pub first_file: Option<(u8, usize)>,
+    /// Clients registered to a chat server, this one included
+    pub clients: Vec<NodeId>,
+}
+
+impl fmt::Display for ServerVisit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "server {}: ", self.server_id)?;
+        if self.is_chat {
+            return write!(f, "chat with clients {:?}", self.clients);
+        }
+        if self.kinds.is_empty() {
+            return write!(f, "no answer");
+        }
+        write!(f, "{:?}, {} files", self.kinds, self.files.len())?;
+        match self.first_file {
+            Some((id, size)) => write!(f, ", file {id} has {size} bytes"),
+            None => Ok(()),
+        }
+    }
+}
+
+/// A client that discovers the network with a flood and asks every server for its content
+pub struct StandInClient {
+    pub id: NodeId,
+    receiver: Receiver<Packet>,
+    senders: HashMap<NodeId, Sender<Packet>>,
+    /// Links found by the floods, in both directions
+    links: HashMap<NodeId, HashSet<NodeId>>,
+    node_types: HashMap<NodeId, NodeType>,
+    assembler: Assembler,
+    /// Fragments waiting for an ack, by session and index, sent again after a NACK
+    sent: HashMap<(u64, u64), Packet>,
+    next_session: u64,
+    /// Time waited for a packet before giving up
+    pub timeout: Duration,
+    /// Servers of the network, the discovery ends as soon as they are all found
+    pub expected_servers: HashSet<NodeId>,
+}
+
+impl StandInClient {
+    pub fn new(
+        id: NodeId,
+        receiver: Receiver<Packet>,
+        senders: HashMap<NodeId, Sender<Packet>>,
+    ) -> Self {
+        StandInClient {
+            id,
+            receiver,
+            senders,
+            links: HashMap::new(),
+            node_types: HashMap::new(),
+            assembler: Assembler::new(),
+            sent: HashMap::new(),
+            // Sessions of different clients never clash on a server
+            next_session: u64::from(id) << 32,
+            timeout: Duration::from_millis(500),
+            expected_servers: HashSet::new(),
+        }
+    }
+
+    /// Discovers the network, then visits every server found
+    pub fn browse(&mut self) -> Vec<ServerVisit> {
+        self.discover();
+        let mut servers: Vec<NodeId> = self
+            .node_types
+            .iter()
+            .filter(|(_, node_type)| matches!(node_type, NodeType::Server))
+            .map(|(id, _)| *id)
+            .collect();
+        servers.sort_unstable();
+        servers
+            .into_iter()
+            .map(|server_id| self.visit(server_id))
+            .collect()
+    }
+
+    /// Sends a flood request and collects the responses until every expected server is found,
+    /// or until the network is quiet if some are unreachable or none is expected.
+    /// Content servers forward flood requests instead of answering them, so a server
+    /// is only found if it's connected to another drone that answers
+    pub fn discover(&mut self) {
+        let flood_id = self.new_session();
+        for sender in self.senders.values() {
+            let _ = sender.send(Packet::new_flood_request(
+                SourceRoutingHeader::empty_route(),
+                flood_id,
+                FloodRequest::initialize(flood_id, self.id, NodeType::Client),
+            ));
+        }
+        while !self.found_expected_servers() {
+            let Ok(packet) = self.receiver.recv_timeout(self.timeout) else {
+                break;
+            };
+            self.handle_packet(packet);
+        }
+    }
+
+    fn found_expected_servers(&self) -> bool {
+        !self.expected_servers.is_empty()
+            && self
+                .expected_servers
+                .iter()
+                .all(|id| matches!(self.node_types.get(id), Some(NodeType::Server)))
+    }
+
+    /// Asks the server for its type and the kinds of content it serves, then for its files
+    /// and the first of them, or registers to it if it's a chat server
+    pub fn visit(&mut self, server_id: NodeId) -> ServerVisit {
+        let mut visit = ServerVisit {
+            server_id,
+            ..ServerVisit::default()
+        };
+        let request = BrowserRequestWrapper::ServerType(ServerTypeRequest::ServerType);
+        match self.request(server_id, &request.stringify()) {
+            Some(Response::Browser(BrowserResponseWrapper::ServerType(
+                ServerTypeResponse::ServerType(server_type),
+            ))) => match server_type {
+                ServerType::Text => visit.kinds = vec![ContentKind::Text],
+                ServerType::Media => visit.kinds = vec![ContentKind::Media],
+                ServerType::Chat => visit.is_chat = true,
+            },
+            _ => return visit,
+        }
+        if !visit.is_chat {
+            // Only a hybrid server serves more than the kind of its type
+            let request = ContentRequestWrapper::Content(ContentRequest::Capabilities);
+            if let Some(Response::Content(ContentResponseWrapper::Content(
+                ContentResponse::ServerCapabilities(kinds),
+            ))) = self.request(server_id, &request.stringify())
+            {
+                visit.kinds = kinds;
+            }
+        }
+
+        if visit.is_chat {
+            let register = ContentRequestWrapper::Chat(ChatRequest::Register);
+            self.request(server_id, &register.stringify());
+            let list = ContentRequestWrapper::Chat(ChatRequest::ClientList);
+            if let Some(Response::Content(ContentResponseWrapper::Chat(
+                ChatResponse::ClientList(clients),
+            ))) = self.request(server_id, &list.stringify())
+            {
+                visit.clients = clients;
+            }
+            return visit;
+        }
+
+        let request = if visit.kinds.len() > 1 {
+            ContentRequestWrapper::Content(ContentRequest::TaggedFileList).stringify()
+        } else {
+            BrowserRequestWrapper::Chat(BrowserRequest::FileList).stringify()
+        };
+        let first_kind = match self.request(server_id, &request) {
+            Some(Response::Browser(BrowserResponseWrapper::Chat(BrowserResponse::FileList(
+                files,
+            )))) => {
+                visit.files = files;
+                visit.kinds[0]
+            }
+            Some(Response::Content(ContentResponseWrapper::Content(
+                ContentResponse::TaggedFileList(files),
+            ))) => {
+                visit.files = files.iter().map(|file| file.id).collect();
+                match files.first() {
+                    Some(file) => file.kind,
+                    None => return visit,
+                }
+            }
+            _ => return visit,
+        };
+        let Some(&id) = visit.files.first() else {
+            return visit;
+        };
+        let request = BrowserRequestWrapper::Chat(match first_kind {
+            ContentKind::Text => BrowserRequest::TextFileRequest(id),
+            ContentKind::Media => BrowserRequest::MediaFileRequest(id),
+        });
+        visit.first_file =
+            match self.request(server_id, &request.stringify()) {
+                Some(Response::Browser(BrowserResponseWrapper::Chat(
+                    BrowserResponse::TextFile(id, text),
+                ))) => Some((id, text.len())),
+                Some(Response::Browser(BrowserResponseWrapper::Chat(
+                    BrowserResponse::MediaFile(id, data),
+                ))) => Some((id, data.len())),
+                Some(Response::Content(ContentResponseWrapper::Content(
+                    ContentResponse::CompactMediaFile { id, data, .. },
+                ))) => messages::decode_media(&data)
+                    .ok()
+                    .map(|data| (id, data.len())),
+                _ => None,
+            };
+        visit
+    }
+
+    /// Sends the request to the server and waits for its response
+    fn request(&mut self, server_id: NodeId, request: &str) -> Option<Response> {
+        let route = self.route_to(server_id)?;
+        let session_id = self.new_session();
+        let fragments =
+            Disassembler::new().disassemble_message(request.as_bytes().to_vec(), session_id);
+        for fragment in fragments {
+            let index = fragment.fragment_index;
+            let packet = Packet::new_fragment(
+                SourceRoutingHeader::new(route.clone(), 1),
+                session_id,
+                fragment,
+            );
+            self.send(packet.clone());
+            self.sent.insert((session_id, index), packet);
+        }
+        while let Ok(packet) = self.receiver.recv_timeout(self.timeout * 4) {
+            if let Some((message_session, message)) = self.handle_packet(packet) {
+                if message_session == session_id {
+                    return Response::parse(&String::from_utf8_lossy(&message));
+                }
+            }
+        }
+        None
+    }
+
+    /// Handles a packet received, returns the message it completes if any
+    fn handle_packet(&mut self, packet: Packet) -> Option<(u64, Vec<u8>)> {
+        match packet.pack_type {
+            PacketType::MsgFragment(fragment) => {
+                let mut hops = packet.routing_header.hops.clone();
+                hops.reverse();
+                self.send(Packet::new_ack(
+                    SourceRoutingHeader::new(hops, 1),
+                    packet.session_id,
+                    fragment.fragment_index,
+                ));
+                self.assembler
+                    .add_fragment(fragment, packet.session_id)
+                    .map(|message| (packet.session_id, message))
+            }
+            PacketType::Ack(ack) => {
+                self.sent.remove(&(packet.session_id, ack.fragment_index));
+                None
+            }
+            PacketType::Nack(nack) => {
+                let key = (packet.session_id, nack.fragment_index);
+                // A dropped fragment is sent again, the others can't be delivered on their route
+                if matches!(nack.nack_type, NackType::Dropped) {
+                    if let Some(fragment) = self.sent.get(&key).cloned() {
+                        self.send(fragment);
+                    }
+                } else {
+                    self.sent.remove(&key);
+                }
+                None
+            }
+            PacketType::FloodRequest(mut flood_request) => {
+                flood_request.increment(self.id, NodeType::Client);
+                self.send(flood_request.generate_response(packet.session_id));
+                None
+            }
+            PacketType::FloodResponse(flood_response) => {
+                for window in flood_response.path_trace.windows(2) {
+                    let ((from, _), (to, _)) = (window[0], window[1]);
+                    self.links.entry(from).or_default().insert(to);
+                    self.links.entry(to).or_default().insert(from);
+                }
+                for (id, node_type) in flood_response.path_trace {
+                    self.node_types.insert(id, node_type);
+                }
+                None
+            }
+        }
+    }
+
+    /// Finds the shortest route to the node that only goes through drones
+    fn route_to(&self, destination: NodeId) -> Option<Vec<NodeId>> {
+        let mut previous: HashMap<NodeId, NodeId> = HashMap::new();
+        let mut queue = VecDeque::from([self.id]);
+        while let Some(node) = queue.pop_front() {
+            if node == destination {
+                let mut route = vec![destination];
+                while let Some(hop) = previous.get(route.last()?) {
+                    route.push(*hop);
+                }
+                route.reverse();
+                return Some(route);
+            }
+            let is_drone = matches!(self.node_types.get(&node), Some(NodeType::Drone));
+            if node != self.id && !is_drone {
+                continue;
+            }
+            for next in self.links.get(&node).into_iter().flatten() {
+                if *next != self.id && !previous.contains_key(next) {
+                    previous.insert(*next, node);
+                    queue.push_back(*next);
+                }
+            }
+        }
+        None
+    }
+
+    /// Sends the packet to the next hop of its route
+    fn send(&self, packet: Packet) {
+        if let Some(sender) = packet
+            .routing_header
+            .current_hop()
+            .and_then(|hop| self.senders.get(&hop))
+        {
+            let _ = sender.send(packet);
+        }
+    }
+
+    fn new_session(&mut self) -> u64 {
+        self.next_session += 1;
+        self.next_session
+    }
+}
+
+/// A response of a content server, in the shared format or in the format of this crate
+enum Response {
+    Browser(BrowserResponseWrapper),
+    Content(ContentResponseWrapper),
+}
+
+impl Response {
+    fn parse(raw: &str) -> Option<Response> {
+        if let Ok(response) = ContentResponseWrapper::from_string(raw) {
+            return Some(Response::Content(response));
+        }
+        BrowserResponseWrapper::from_string(raw.to_string())
+            .ok()
+            .map(Response::Browser)
+    }
+}
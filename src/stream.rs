@@ -0,0 +1,211 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use std::io::{self, Read, Seek, SeekFrom};
+
+/// Size of the chunks read from the source of a stream
+const CHUNK_SIZE: usize = 8 * 1024;
+
+/// Source of a streamed payload, read once to measure it and, rewound, once to send it
+pub trait Source: Read + Seek + Send {}
+
+impl<T: Read + Seek + Send> Source for T {}
+
+/// Opens the source of a streamed payload
+pub type OpenSource = Box<dyn Fn() -> io::Result<Box<dyn Source>> + Send>;
+
+/// How the bytes of the source are written in the JSON of the response
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Payload {
+    /// An array of numbers, as serde serializes a `Vec<u8>`
+    Bytes,
+    /// The content of a string, the source must be UTF-8
+    Text,
+    /// The content of a string holding the source encoded in base64
+    Base64,
+}
+
+/// A serialized JSON response whose payload is read from its source in chunks,
+/// so the whole response is never held in memory
+pub struct ResponseStream {
+    suffix: Vec<u8>,
+    payload: Payload,
+    source: Box<dyn Source>,
+    /// Bytes of the source not yet read, the source must not grow or shrink once measured
+    remaining: u64,
+    /// Serialized bytes not yet returned
+    buffer: Vec<u8>,
+    /// Bytes of the source read but not yet encoded in base64, fewer than 3
+    pending: Vec<u8>,
+    /// Whether the next byte of the payload is the first one
+    first: bool,
+    /// Whether the source has been read to the end
+    exhausted: bool,
+    /// Serialized bytes already returned
+    returned: u64,
+    len: u64,
+}
+
+impl ResponseStream {
+    /// Creates the stream of the response that serializes as `prefix`, payload, `suffix`,
+    /// the source is read once to measure the length of the response and then rewound
+    /// # Errors
+    /// Returns the error raised reading or rewinding the source
+    pub fn new(
+        prefix: &str,
+        suffix: &str,
+        payload: Payload,
+        mut source: Box<dyn Source>,
+    ) -> io::Result<ResponseStream> {
+        let mut payload_len = 0;
+        let mut count = 0u64;
+        let start = source.stream_position()?;
+        let mut chunk = vec![0; CHUNK_SIZE];
+        loop {
+            let read = source.read(&mut chunk)?;
+            if read == 0 {
+                break;
+            }
+            count += read as u64;
+            payload_len += chunk[..read]
+                .iter()
+                .map(|byte| serialized_len(*byte, payload))
+                .sum::<u64>();
+        }
+        match payload {
+            // The numbers of an array are separated by commas
+            Payload::Bytes => payload_len += count.saturating_sub(1),
+            // Every 3 bytes, or fewer at the end, become 4 characters
+            Payload::Base64 => payload_len = count.div_ceil(3) * 4,
+            Payload::Text => {}
+        }
+        source.seek(SeekFrom::Start(start))?;
+        Ok(ResponseStream {
+            suffix: suffix.as_bytes().to_vec(),
+            payload,
+            source,
+            remaining: count,
+            buffer: prefix.as_bytes().to_vec(),
+            pending: Vec::new(),
+            first: true,
+            exhausted: false,
+            returned: 0,
+            len: (prefix.len() + suffix.len()) as u64 + payload_len,
+        })
+    }
+
+    /// Splits a serialized response around its payload, given by `placeholder`
+    /// (`""` for an empty string or `[]` for an empty array), returning the prefix and the suffix
+    pub fn split_response(response: &str, placeholder: &str) -> Option<(String, String)> {
+        let position = response.rfind(placeholder)?;
+        let middle = position + placeholder.len() / 2;
+        Some((
+            response[..middle].to_string(),
+            response[middle..].to_string(),
+        ))
+    }
+
+    /// Returns the length in bytes of the whole serialized response
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Checks if the serialized response is empty
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the next `size` bytes of the response, fewer at the end
+    /// # Errors
+    /// Returns the error raised reading the source, or `InvalidData` if the source
+    /// changed since it was measured
+    pub fn next_bytes(&mut self, size: usize) -> io::Result<Vec<u8>> {
+        let mut chunk = vec![0; CHUNK_SIZE];
+        while self.buffer.len() < size && !self.exhausted {
+            // Never read past the measured length
+            let limit = chunk
+                .len()
+                .min(usize::try_from(self.remaining).unwrap_or(usize::MAX));
+            let read = match limit {
+                0 => 0,
+                limit => self.source.read(&mut chunk[..limit])?,
+            };
+            self.remaining -= read as u64;
+            if read == 0 {
+                if self.remaining > 0 {
+                    return Err(changed_source());
+                }
+                self.exhausted = true;
+                // Encode the last bytes with the padding
+                let pending = std::mem::take(&mut self.pending);
+                self.buffer
+                    .extend_from_slice(STANDARD.encode(pending).as_bytes());
+                self.buffer.append(&mut self.suffix);
+                break;
+            }
+            for byte in &chunk[..read] {
+                match self.payload {
+                    Payload::Bytes => {
+                        if !self.first {
+                            self.buffer.push(b',');
+                        }
+                        self.buffer.extend_from_slice(byte.to_string().as_bytes());
+                    }
+                    Payload::Text => escape_into(*byte, &mut self.buffer),
+                    Payload::Base64 => self.pending.push(*byte),
+                }
+                self.first = false;
+            }
+            // Encode the whole groups of 3 bytes, the others wait for the next chunk
+            let whole = self.pending.len() / 3 * 3;
+            let group: Vec<u8> = self.pending.drain(..whole).collect();
+            self.buffer
+                .extend_from_slice(STANDARD.encode(group).as_bytes());
+        }
+        // The length sent with the first fragments must stay right
+        if self.exhausted && self.returned + self.buffer.len() as u64 != self.len {
+            return Err(changed_source());
+        }
+        let size = size.min(self.buffer.len());
+        self.returned += size as u64;
+        Ok(self.buffer.drain(..size).collect())
+    }
+}
+
+/// Error returned when the source of a stream changes while it's sent
+fn changed_source() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        "the streamed content changed since it was measured",
+    )
+}
+
+/// Returns the length of a byte of the payload once serialized
+fn serialized_len(byte: u8, payload: Payload) -> u64 {
+    match payload {
+        Payload::Bytes if byte >= 100 => 3,
+        Payload::Bytes if byte >= 10 => 2,
+        Payload::Bytes => 1,
+        // Measured on the whole payload
+        Payload::Base64 => 0,
+        Payload::Text => match byte {
+            b'"' | b'\\' | b'\n' | b'\r' | b'\t' | 0x08 | 0x0c => 2,
+            0x00..=0x1f => 6,
+            _ => 1,
+        },
+    }
+}
+
+/// Escapes a byte of a UTF-8 string as `serde_json` does, multi-byte characters are not escaped
+fn escape_into(byte: u8, buffer: &mut Vec<u8>) {
+    match byte {
+        b'"' => buffer.extend_from_slice(b"\\\""),
+        b'\\' => buffer.extend_from_slice(b"\\\\"),
+        b'\n' => buffer.extend_from_slice(b"\\n"),
+        b'\r' => buffer.extend_from_slice(b"\\r"),
+        b'\t' => buffer.extend_from_slice(b"\\t"),
+        0x08 => buffer.extend_from_slice(b"\\b"),
+        0x0c => buffer.extend_from_slice(b"\\f"),
+        0x00..=0x1f => buffer.extend_from_slice(format!("\\u{byte:04x}").as_bytes()),
+        _ => buffer.push(byte),
+    }
+}
@@ -0,0 +1,193 @@
+#[cfg(test)]
+#[allow(unused)]
+pub mod chat_server_test {
+    use std::collections::HashMap;
+    use std::time::Duration;
+
+    use crossbeam_channel::{unbounded, Receiver};
+    use rustafarian_shared::assembler::{assembler::Assembler, disassembler::Disassembler};
+    use rustafarian_shared::messages::{
+        browser_messages::{BrowserRequest, BrowserRequestWrapper},
+        general_messages::{DroneSend, ServerType},
+    };
+    use wg_2024::network::SourceRoutingHeader;
+    use wg_2024::packet::{Packet, PacketType};
+
+    use crate::content_server::ContentServer;
+    use crate::content_store::MemoryStore;
+    use crate::messages::{
+        ChatRequest, ChatResponse, ContentError, ContentRequestWrapper, ContentResponse,
+        ContentResponseWrapper,
+    };
+    use crate::tests::utils::build_server_with_stores;
+
+    /// Sends a request to the server as if it came from the client through drone 2
+    fn send_from(server: &mut ContentServer, client: u8, request: &ContentRequestWrapper) {
+        let session_id = u64::from(client) * 100 + server.sent_packets.len() as u64;
+        let fragments = Disassembler::new()
+            .disassemble_message(request.stringify().as_bytes().to_vec(), session_id);
+        for fragment in fragments {
+            let packet = Packet {
+                routing_header: SourceRoutingHeader::new(vec![client, 2, 1], 1),
+                session_id,
+                pack_type: PacketType::MsgFragment(fragment),
+            };
+            server.handle_drone_packets(Ok(packet));
+        }
+    }
+
+    /// Reassembles the next message sent by the server, with the client it's addressed to
+    fn receive(neighbor: &Receiver<Packet>) -> (u8, ContentResponseWrapper) {
+        let mut assembler = Assembler::new();
+        while let Ok(packet) = neighbor.recv_timeout(Duration::from_millis(500)) {
+            let destination = packet.routing_header.destination().unwrap();
+            if let PacketType::MsgFragment(fragment) = packet.pack_type {
+                if let Some(message) = assembler.add_fragment(fragment, packet.session_id) {
+                    let response =
+                        ContentResponseWrapper::from_string(&String::from_utf8(message).unwrap())
+                            .expect("Error deserializing the response");
+                    return (destination, response);
+                }
+            }
+        }
+        panic!("No response from the server");
+    }
+
+    #[test]
+    fn chat_relay_test() {
+        let (mut server, neighbor) = build_server_with_stores(
+            Box::new(MemoryStore::new()),
+            Box::new(MemoryStore::new()),
+            ServerType::Chat,
+        );
+
+        for client in [21, 22] {
+            send_from(
+                &mut server,
+                client,
+                &ContentRequestWrapper::Chat(ChatRequest::Register),
+            );
+            assert_eq!(
+                receive(&neighbor.1),
+                (
+                    client,
+                    ContentResponseWrapper::Chat(ChatResponse::Registered)
+                )
+            );
+        }
+
+        send_from(
+            &mut server,
+            21,
+            &ContentRequestWrapper::Chat(ChatRequest::ClientList),
+        );
+        assert_eq!(
+            receive(&neighbor.1),
+            (
+                21,
+                ContentResponseWrapper::Chat(ChatResponse::ClientList(vec![21, 22]))
+            )
+        );
+
+        let request = ContentRequestWrapper::Chat(ChatRequest::SendMessage {
+            to: 22,
+            message: "Hello 22".to_string(),
+        });
+        send_from(&mut server, 21, &request);
+        assert_eq!(
+            receive(&neighbor.1),
+            (
+                22,
+                ContentResponseWrapper::Chat(ChatResponse::MessageFrom {
+                    from: 21,
+                    message: "Hello 22".to_string(),
+                })
+            )
+        );
+        assert_eq!(
+            receive(&neighbor.1),
+            (
+                21,
+                ContentResponseWrapper::Chat(ChatResponse::MessageSent { to: 22 })
+            )
+        );
+
+        // Messages to unregistered clients are refused
+        let request = ContentRequestWrapper::Chat(ChatRequest::SendMessage {
+            to: 23,
+            message: "Hello 23".to_string(),
+        });
+        send_from(&mut server, 21, &request);
+        let (client, response) = receive(&neighbor.1);
+        assert_eq!(client, 21);
+        assert!(matches!(
+            response,
+            ContentResponseWrapper::Content(ContentResponse::Error(ContentError::BadRequest(_)))
+        ));
+    }
+
+    #[test]
+    fn chat_server_wrong_requests_test() {
+        let (mut server, neighbor) = build_server_with_stores(
+            Box::new(MemoryStore::new()),
+            Box::new(MemoryStore::new()),
+            ServerType::Chat,
+        );
+
+        // A chat server has no files, it answers instead of exiting
+        let request = BrowserRequestWrapper::Chat(BrowserRequest::FileList);
+        let fragments =
+            Disassembler::new().disassemble_message(request.stringify().into_bytes(), 1);
+        for fragment in fragments {
+            server.handle_drone_packets(Ok(Packet {
+                routing_header: SourceRoutingHeader::new(vec![21, 2, 1], 1),
+                session_id: 1,
+                pack_type: PacketType::MsgFragment(fragment),
+            }));
+        }
+        let (_, response) = receive(&neighbor.1);
+        assert!(matches!(
+            response,
+            ContentResponseWrapper::Content(ContentResponse::Error(ContentError::WrongServerType(
+                _
+            )))
+        ));
+
+        // A text server doesn't handle chat requests
+        let (mut server, neighbor) = build_server_with_stores(
+            Box::new(MemoryStore::new()),
+            Box::new(MemoryStore::new()),
+            ServerType::Text,
+        );
+        send_from(
+            &mut server,
+            21,
+            &ContentRequestWrapper::Chat(ChatRequest::Register),
+        );
+        let (_, response) = receive(&neighbor.1);
+        assert!(matches!(
+            response,
+            ContentResponseWrapper::Content(ContentResponse::Error(ContentError::WrongServerType(
+                _
+            )))
+        ));
+    }
+
+    #[test]
+    fn chat_server_new_test() {
+        // No content directory is needed
+        let server = ContentServer::new(
+            1,
+            HashMap::new(),
+            unbounded().1,
+            unbounded().1,
+            unbounded().0,
+            "missing_files",
+            "missing_media",
+            ServerType::Chat,
+            false,
+        )
+        .expect("Failed to create the server");
+        assert!(server.clients.is_empty());
+    }
+}
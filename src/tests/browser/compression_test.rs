@@ -0,0 +1,95 @@
+#[cfg(test)]
+#[allow(unused)]
+pub mod compression_test {
+    use rustafarian_shared::messages::{
+        browser_messages::{
+            BrowserRequest, BrowserRequestWrapper, BrowserResponse, BrowserResponseWrapper,
+        },
+        general_messages::{DroneSend, ServerType},
+    };
+
+    use crate::compression::{self, ContentEncoding};
+    use crate::content_store::MemoryStore;
+    use crate::messages::{ContentRequestWrapper, ContentResponseWrapper};
+    use crate::tests::utils::{
+        build_server_with_stores, receive_message, receive_response, send_request,
+    };
+
+    #[test]
+    fn compression_round_trip_test() {
+        let text = "Lorem ipsum dolor sit amet, consectetur adipiscing elit. ".repeat(20);
+        for encoding in [ContentEncoding::Gzip, ContentEncoding::Deflate] {
+            let compressed = compression::compress(text.as_bytes(), encoding).unwrap();
+            assert!(compressed.len() * 3 < text.len());
+            assert_eq!(
+                compression::decompress(&compressed, encoding).unwrap(),
+                text.as_bytes()
+            );
+
+            let data = compression::encode_message(&text, encoding).unwrap();
+            assert_eq!(compression::decode_message(&data, encoding).unwrap(), text);
+        }
+        assert!(compression::decode_message("not base64!", ContentEncoding::Gzip).is_err());
+    }
+
+    #[test]
+    fn compressed_text_request_test() {
+        let text = "Lorem ipsum dolor sit amet, consectetur adipiscing elit. ".repeat(20);
+        let mut files = MemoryStore::new();
+        files.insert(1, text.clone());
+        let (mut server, neighbor) = build_server_with_stores(
+            Box::new(files),
+            Box::new(MemoryStore::new()),
+            ServerType::Text,
+        );
+
+        let request = ContentRequestWrapper::AcceptEncoding {
+            encodings: vec![ContentEncoding::Gzip, ContentEncoding::Deflate],
+            request: BrowserRequestWrapper::Chat(BrowserRequest::TextFileRequest(1)).stringify(),
+        };
+        send_request(&mut server, &request.stringify(), 1);
+
+        let response = receive_response(&neighbor.1);
+        let ContentResponseWrapper::Encoded { encoding, data } = response else {
+            panic!("Unexpected response");
+        };
+        assert_eq!(encoding, ContentEncoding::Gzip);
+        let response = compression::decode_message(&data, encoding).unwrap();
+        match BrowserResponseWrapper::from_string(response) {
+            Ok(BrowserResponseWrapper::Chat(BrowserResponse::TextFile(1, received))) => {
+                assert_eq!(received, text);
+            }
+            _ => panic!("Unexpected response"),
+        }
+
+        // A later request without the wrapper is answered uncompressed
+        let request = BrowserRequestWrapper::Chat(BrowserRequest::TextFileRequest(1));
+        send_request(&mut server, &request.stringify(), 2);
+        let response = receive_message(&neighbor.1).expect("No response from the server");
+        assert!(BrowserResponseWrapper::from_string(String::from_utf8(response).unwrap()).is_ok());
+    }
+
+    #[test]
+    fn small_response_not_compressed_test() {
+        let (mut server, neighbor) = build_server_with_stores(
+            Box::new(MemoryStore::new()),
+            Box::new(MemoryStore::new()),
+            ServerType::Text,
+        );
+
+        let request = ContentRequestWrapper::AcceptEncoding {
+            encodings: vec![ContentEncoding::Deflate],
+            request: BrowserRequestWrapper::Chat(BrowserRequest::FileList).stringify(),
+        };
+        send_request(&mut server, &request.stringify(), 1);
+
+        // Compressing the short file list would make it bigger
+        let response = receive_message(&neighbor.1).expect("No response from the server");
+        match BrowserResponseWrapper::from_string(String::from_utf8(response).unwrap()) {
+            Ok(BrowserResponseWrapper::Chat(BrowserResponse::FileList(files))) => {
+                assert!(files.is_empty());
+            }
+            _ => panic!("Unexpected response"),
+        }
+    }
+}
@@ -0,0 +1,161 @@
+#[cfg(test)]
+#[allow(unused)]
+pub mod config_test {
+    use std::time::Duration;
+
+    use crossbeam_channel::unbounded;
+    use rustafarian_shared::messages::{
+        browser_messages::{BrowserRequest, BrowserRequestWrapper},
+        general_messages::{DroneSend, ServerType},
+    };
+    use wg_2024::network::SourceRoutingHeader;
+    use wg_2024::packet::{Nack, NackType, Packet, PacketType};
+
+    use crate::builder::ContentServerBuilder;
+    use crate::config::ContentServerConfig;
+    use crate::content_store::{ContentStore, MemoryStore};
+    use crate::error::ContentServerError;
+    use crate::logging::LogLevelFilter;
+    use crate::messages::{ContentError, ContentResponse, ContentResponseWrapper, MediaEncoding};
+    use crate::selection::SelectionPolicy;
+    use crate::tests::utils::{
+        build_server_with_stores, receive_message, receive_response, send_request,
+    };
+
+    #[test]
+    fn config_parse_test() {
+        let config = ContentServerConfig::from_toml(
+            r#"
+            server_id = 3
+            server_type = "Media"
+            media_selection = { FirstSorted = 5 }
+            media_encoding = "Base64"
+            log_level = "Info"
+            flood_interval_ms = 500
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.server_id, 3);
+        assert!(matches!(config.server_type, ServerType::Media));
+        assert_eq!(config.media_directory, "media");
+        assert_eq!(
+            config.options.media_selection,
+            SelectionPolicy::FirstSorted(5)
+        );
+        assert_eq!(config.options.media_encoding, MediaEncoding::Base64);
+        assert_eq!(config.options.log_level, LogLevelFilter::Info);
+        assert_eq!(config.options.flood_interval, Duration::from_millis(500));
+        // Missing options keep their default
+        assert_eq!(config.options.file_selection, SelectionPolicy::Random(10));
+        assert_eq!(config.options.rescan_interval, Some(Duration::from_secs(5)));
+
+        let config = ContentServerConfig::from_json(
+            r#"{"server_id": 4, "file_selection": "All", "rescan_interval_ms": null, "hybrid": true}"#,
+        )
+        .unwrap();
+        assert_eq!(config.server_id, 4);
+        assert_eq!(config.options.file_selection, SelectionPolicy::All);
+        assert_eq!(config.options.rescan_interval, None);
+        assert!(config.options.hybrid);
+
+        assert!(matches!(
+            ContentServerConfig::from_toml("server_id = 300"),
+            Err(ContentServerError::InvalidConfig(_))
+        ));
+    }
+
+    #[test]
+    fn config_disable_rescan_test() {
+        // TOML has no null, a zero interval turns the rescans off
+        let config = ContentServerConfig::from_toml("rescan_interval_ms = 0").unwrap();
+        assert_eq!(config.options.rescan_interval, None);
+
+        // And it's written back the same way
+        let written = toml::to_string(&config).unwrap();
+        assert!(written.contains("rescan_interval_ms = 0"));
+        let config = ContentServerConfig::from_toml(&written).unwrap();
+        assert_eq!(config.options.rescan_interval, None);
+    }
+
+    #[test]
+    fn config_builder_test() {
+        let missing = ContentServerBuilder::new(1).receiver(unbounded().1).build();
+        assert!(matches!(
+            missing,
+            Err(ContentServerError::MissingChannel(_))
+        ));
+
+        let server = ContentServerBuilder::new(1)
+            .server_type(ServerType::Text)
+            .file_directory("files")
+            .sender(2, unbounded().0)
+            .receiver(unbounded().1)
+            .controller(unbounded().1, unbounded().0)
+            .configure(|options| {
+                options.file_selection = SelectionPolicy::Ids(vec![1, 2]);
+                options.log_level = LogLevelFilter::Error;
+            })
+            .build()
+            .expect("Failed to create the server");
+        assert_eq!(server.files.list(), vec![1, 2]);
+        assert_eq!(server.options.log_level, LogLevelFilter::Error);
+        assert!(server.senders.contains_key(&2));
+    }
+
+    #[test]
+    fn config_retry_limit_test() {
+        let mut files = MemoryStore::new();
+        files.insert(1, "One");
+        let (mut server, neighbor) = build_server_with_stores(
+            Box::new(files),
+            Box::new(MemoryStore::new()),
+            ServerType::Text,
+        );
+        server.options.max_fragment_retries = 1;
+
+        let request = BrowserRequestWrapper::Chat(BrowserRequest::TextFileRequest(1));
+        send_request(&mut server, &request.stringify(), 7);
+        receive_message(&neighbor.1).expect("No response from the server");
+
+        let nack = Packet {
+            routing_header: SourceRoutingHeader::new(vec![2, 1], 1),
+            session_id: 7,
+            pack_type: PacketType::Nack(Nack {
+                fragment_index: 0,
+                nack_type: NackType::Dropped,
+            }),
+        };
+        // The fragment is sent again once, then the server gives up
+        server.handle_drone_packets(Ok(nack.clone()));
+        assert!(matches!(
+            neighbor.1.try_recv().map(|packet| packet.pack_type),
+            Ok(PacketType::MsgFragment(_))
+        ));
+        server.handle_drone_packets(Ok(nack.clone()));
+
+        // The session is aborted and the client is told with an error in a new session
+        assert_eq!(
+            receive_response(&neighbor.1),
+            ContentResponseWrapper::Content(ContentResponse::Error(ContentError::SessionAborted(
+                7
+            )))
+        );
+        assert!(!server.sent_packets.contains_key(&7));
+        let error_session = *server
+            .sent_packets
+            .keys()
+            .next()
+            .expect("The error is not kept until it's acknowledged");
+
+        // The error is not sent again if it's given up too
+        let nack = Packet {
+            session_id: error_session,
+            ..nack
+        };
+        server.handle_drone_packets(Ok(nack.clone()));
+        receive_message(&neighbor.1).expect("No error from the server");
+        server.handle_drone_packets(Ok(nack));
+        assert!(neighbor.1.try_recv().is_err());
+        assert!(server.sent_packets.is_empty());
+    }
+}
@@ -0,0 +1,83 @@
+#[cfg(test)]
+#[allow(unused)]
+pub mod error_response_test {
+    use crossbeam_channel::Receiver;
+    use rustafarian_shared::messages::{
+        browser_messages::{BrowserRequest, BrowserRequestWrapper},
+        general_messages::{DroneSend, ServerType},
+    };
+    use wg_2024::packet::Packet;
+
+    use crate::content_server::ContentServer;
+    use crate::content_store::MemoryStore;
+    use crate::messages::{
+        ContentError, ContentRequest, ContentRequestWrapper, ContentResponse,
+        ContentResponseWrapper,
+    };
+    use crate::tests::utils::{build_server_with_stores, receive_response, send_request};
+
+    fn request_error(
+        server: &mut ContentServer,
+        neighbor: &Receiver<Packet>,
+        request: &str,
+    ) -> ContentError {
+        send_request(server, request, 1);
+        let ContentResponseWrapper::Content(ContentResponse::Error(error)) =
+            receive_response(neighbor)
+        else {
+            panic!("Unexpected response");
+        };
+        error
+    }
+
+    #[test]
+    fn text_server_errors_test() {
+        let (mut server, neighbor) = build_server_with_stores(
+            Box::new(MemoryStore::new()),
+            Box::new(MemoryStore::new()),
+            ServerType::Text,
+        );
+
+        let request = BrowserRequestWrapper::Chat(BrowserRequest::TextFileRequest(9));
+        let error = request_error(&mut server, &neighbor.1, &request.stringify());
+        assert_eq!(error, ContentError::NotFound(9));
+        assert_eq!(error.to_string(), "File with ID '9' not found");
+
+        let request = BrowserRequestWrapper::Chat(BrowserRequest::MediaFileRequest(1));
+        let error = request_error(&mut server, &neighbor.1, &request.stringify());
+        assert!(matches!(error, ContentError::WrongServerType(_)));
+
+        let error = request_error(&mut server, &neighbor.1, "{\"Unknown\":1}");
+        assert!(matches!(error, ContentError::BadRequest(_)));
+    }
+
+    #[test]
+    fn media_server_errors_test() {
+        let mut media = MemoryStore::new();
+        media.insert(2, b"not an image".to_vec());
+        let (mut server, neighbor) = build_server_with_stores(
+            Box::new(MemoryStore::new()),
+            Box::new(media),
+            ServerType::Media,
+        );
+
+        let request = BrowserRequestWrapper::Chat(BrowserRequest::TextFileRequest(2));
+        let error = request_error(&mut server, &neighbor.1, &request.stringify());
+        assert!(matches!(error, ContentError::WrongServerType(_)));
+
+        let request = BrowserRequestWrapper::Chat(BrowserRequest::MediaFileRequest(2));
+        let error = request_error(&mut server, &neighbor.1, &request.stringify());
+        assert!(matches!(error, ContentError::InternalError(_)));
+
+        let request = ContentRequestWrapper::Content(ContentRequest::MediaFile {
+            id: 2,
+            format: Some("image/unknown".to_string()),
+        });
+        let error = request_error(&mut server, &neighbor.1, &request.stringify());
+        assert!(matches!(error, ContentError::BadRequest(_)));
+
+        let request = ContentRequestWrapper::Content(ContentRequest::Thumbnail(7));
+        let error = request_error(&mut server, &neighbor.1, &request.stringify());
+        assert_eq!(error, ContentError::NotFound(7));
+    }
+}
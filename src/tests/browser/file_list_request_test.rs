@@ -49,7 +49,7 @@ pub mod file_list_request_test {
         }
 
         let received_packet = neighbor.1.recv().unwrap();
-        let expected_ids: Vec<u8> = server.files.keys().cloned().collect();
+        let expected_ids: Vec<u8> = server.files.list();
         let expected_response =
             BrowserResponseWrapper::Chat(BrowserResponse::FileList(expected_ids.clone()));
 
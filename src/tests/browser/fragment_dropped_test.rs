@@ -24,10 +24,10 @@ pub mod fragment_dropped_test {
     fn fragment_dropped_test() {
         let (mut server, neighbor, _, _) = build_server();
 
-        let (&file_id, file_content) = server
+        let file_id = *server
             .files
-            .iter()
-            .next()
+            .list()
+            .first()
             .expect("Nessun file disponibile nel server.");
         println!("File ID selezionato: {}", 2);
         let file_request = BrowserRequestWrapper::Chat(BrowserRequest::TextFileRequest(2));
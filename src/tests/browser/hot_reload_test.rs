@@ -0,0 +1,111 @@
+#[cfg(test)]
+#[allow(unused)]
+pub mod hot_reload_test {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use crossbeam_channel::unbounded;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use rustafarian_shared::messages::general_messages::ServerType;
+
+    use crate::content_server::ContentServerOptions;
+    use crate::content_store::ContentStore;
+    use crate::messages::{ContentChanges, ContentEvent};
+    use crate::response_cache::ResponseKey;
+    use crate::selection::SelectionPolicy;
+    use crate::tests::utils::{build_server_in, temp_content_dir};
+
+    #[test]
+    fn hot_reload_changes_test() {
+        let directory = temp_content_dir("hot_reload");
+        fs::write(directory.join("1.txt"), "One").unwrap();
+        fs::write(directory.join("2.txt"), "Two").unwrap();
+        fs::write(directory.join("3.txt"), "Three").unwrap();
+
+        let options = ContentServerOptions {
+            file_selection: SelectionPolicy::All,
+            ..ContentServerOptions::default()
+        };
+        let (mut server, _neighbor) = build_server_in(&directory, ServerType::Text, options);
+        let (listener, events) = unbounded();
+        server.content_listener = Some(listener);
+        server
+            .responses
+            .insert(ResponseKey::TextFile(2), "Cached".to_string());
+
+        // Nothing changed yet
+        assert_eq!(server.reload_content(), Some(ContentChanges::default()));
+        assert!(events.try_recv().is_err());
+
+        fs::write(directory.join("2.txt"), "Two, edited").unwrap();
+        fs::remove_file(directory.join("3.txt")).unwrap();
+        fs::write(directory.join("4.txt"), "Four").unwrap();
+        let expected = ContentChanges {
+            added: vec![4],
+            modified: vec![2],
+            removed: vec![3],
+        };
+        assert_eq!(server.reload_content(), Some(expected.clone()));
+
+        assert_eq!(server.files.list(), vec![1, 2, 4]);
+        assert_eq!(server.files.get(2).unwrap(), b"Two, edited");
+        assert!(server.responses.is_empty());
+        assert_eq!(
+            events.try_recv().expect("Controller not notified"),
+            ContentEvent::ContentChanged {
+                server_id: 1,
+                changes: expected,
+            }
+        );
+    }
+
+    #[test]
+    fn hot_reload_unchanged_stamp_test() {
+        let directory = temp_content_dir("hot_reload_stamp");
+        fs::write(directory.join("1.txt"), "One").unwrap();
+        let (mut server, _neighbor) = build_server_in(
+            &directory,
+            ServerType::Text,
+            ContentServerOptions::default(),
+        );
+        let digest = server.files.metadata(1).unwrap().sha256;
+
+        // A file with the same size and modification time is not hashed again
+        let path = directory.join("1.txt");
+        let modified = fs::metadata(&path).unwrap().modified().unwrap();
+        fs::write(&path, "Eno").unwrap();
+        fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+        assert_eq!(server.reload_content(), Some(ContentChanges::default()));
+        assert_eq!(server.files.metadata(1).unwrap().sha256, digest);
+
+        // Once its stamp changes the new content is seen
+        fs::write(&path, "One, edited").unwrap();
+        let changes = server.reload_content().unwrap();
+        assert_eq!(changes.modified, vec![1]);
+        assert_ne!(server.files.metadata(1).unwrap().sha256, digest);
+    }
+
+    #[test]
+    fn hot_reload_random_selection_test() {
+        let candidates = |ids: &[u8]| -> Vec<(u8, PathBuf)> {
+            ids.iter().map(|id| (*id, PathBuf::new())).collect()
+        };
+        let ids = |selected: Vec<(u8, PathBuf)>| -> Vec<u8> {
+            selected.into_iter().map(|(id, _)| id).collect()
+        };
+        let policy = SelectionPolicy::Random(3);
+        let mut rng = StdRng::seed_from_u64(7);
+
+        // The files still present are kept, a new one replaces the removed one
+        let selected = ids(policy.reapply(candidates(&[1, 2, 4, 5, 6, 7]), &[1, 2, 3], &mut rng));
+        assert_eq!(selected.len(), 3);
+        assert!(selected.starts_with(&[1, 2]));
+        assert!([4, 5, 6, 7].contains(&selected[2]));
+    }
+}
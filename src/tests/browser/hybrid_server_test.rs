@@ -0,0 +1,213 @@
+#[cfg(test)]
+#[allow(unused)]
+pub mod hybrid_server_test {
+    use std::collections::HashMap;
+    use std::fs;
+
+    use crossbeam_channel::unbounded;
+    use image::{DynamicImage, ImageFormat, RgbImage};
+    use rustafarian_shared::messages::{
+        browser_messages::{
+            BrowserRequest, BrowserRequestWrapper, BrowserResponse, BrowserResponseWrapper,
+        },
+        general_messages::{DroneSend, ServerType, ServerTypeRequest, ServerTypeResponse},
+    };
+
+    use crate::content_server::{ContentServer, ContentServerOptions};
+    use crate::content_store::{ContentStore, MemoryStore};
+    use crate::media;
+    use crate::messages::{
+        ContentChanges, ContentError, ContentKind, ContentRequest, ContentResponse,
+        ContentResponseWrapper, TaggedFile,
+    };
+    use crate::selection::SelectionPolicy;
+    use crate::tests::utils::{
+        build_server_with_stores, media_server_with, receive_message, receive_response,
+        request_content, send_request, temp_content_dir,
+    };
+
+    fn image() -> Vec<u8> {
+        let image = DynamicImage::ImageRgb8(RgbImage::new(4, 4));
+        media::encode(&image, ImageFormat::Png).unwrap()
+    }
+
+    #[test]
+    fn hybrid_requests_test() {
+        let (mut server, neighbor) = media_server_with(vec![(1, image()), (2, image())]);
+        let mut files = MemoryStore::new();
+        files.insert(1, "Hybrid text");
+        server.files = Box::new(files);
+        server.options.hybrid = true;
+
+        // The shared requests get the responses a stock client understands
+        let request = BrowserRequestWrapper::ServerType(ServerTypeRequest::ServerType);
+        send_request(&mut server, &request.stringify(), 1);
+        let response = receive_message(&neighbor.1).expect("No response from the server");
+        match BrowserResponseWrapper::from_string(String::from_utf8(response).unwrap()) {
+            Ok(BrowserResponseWrapper::ServerType(ServerTypeResponse::ServerType(
+                ServerType::Media,
+            ))) => {}
+            _ => panic!("Unexpected response"),
+        }
+
+        let request = BrowserRequestWrapper::Chat(BrowserRequest::FileList);
+        send_request(&mut server, &request.stringify(), 2);
+        let response = receive_message(&neighbor.1).expect("No response from the server");
+        match BrowserResponseWrapper::from_string(String::from_utf8(response).unwrap()) {
+            Ok(BrowserResponseWrapper::Chat(BrowserResponse::FileList(files))) => {
+                assert_eq!(files, vec![1, 2]);
+            }
+            _ => panic!("Unexpected response"),
+        }
+
+        // Both kinds are only advertised through the content requests
+        assert_eq!(
+            request_content(&mut server, &neighbor.1, ContentRequest::Capabilities, 3),
+            ContentResponse::ServerCapabilities(vec![ContentKind::Text, ContentKind::Media])
+        );
+        let tagged = |kind, id| TaggedFile { kind, id };
+        assert_eq!(
+            request_content(&mut server, &neighbor.1, ContentRequest::TaggedFileList, 4),
+            ContentResponse::TaggedFileList(vec![
+                tagged(ContentKind::Text, 1),
+                tagged(ContentKind::Media, 1),
+                tagged(ContentKind::Media, 2),
+            ])
+        );
+
+        let request = BrowserRequestWrapper::Chat(BrowserRequest::TextFileRequest(1));
+        send_request(&mut server, &request.stringify(), 5);
+        let response = receive_message(&neighbor.1).expect("No response from the server");
+        match BrowserResponseWrapper::from_string(String::from_utf8(response).unwrap()) {
+            Ok(BrowserResponseWrapper::Chat(BrowserResponse::TextFile(1, text))) => {
+                assert_eq!(text, "Hybrid text");
+            }
+            _ => panic!("Unexpected response"),
+        }
+
+        let request = BrowserRequestWrapper::Chat(BrowserRequest::MediaFileRequest(2));
+        send_request(&mut server, &request.stringify(), 6);
+        let response = receive_message(&neighbor.1).expect("No response from the server");
+        match BrowserResponseWrapper::from_string(String::from_utf8(response).unwrap()) {
+            Ok(BrowserResponseWrapper::Chat(BrowserResponse::MediaFile(2, data))) => {
+                assert_eq!(media::dimensions(&data).unwrap(), (4, 4));
+            }
+            _ => panic!("Unexpected response"),
+        }
+    }
+
+    #[test]
+    fn hybrid_directories_test() {
+        let files = temp_content_dir("hybrid_files");
+        let media_directory = temp_content_dir("hybrid_media");
+        fs::write(files.join("1.txt"), "One").unwrap();
+        fs::write(media_directory.join("5.png"), image()).unwrap();
+
+        let options = ContentServerOptions {
+            file_selection: SelectionPolicy::All,
+            media_selection: SelectionPolicy::All,
+            hybrid: true,
+            ..ContentServerOptions::default()
+        };
+        let mut server = ContentServer::with_options(
+            1,
+            HashMap::new(),
+            unbounded().1,
+            unbounded().1,
+            unbounded().0,
+            files.to_str().unwrap(),
+            media_directory.to_str().unwrap(),
+            ServerType::Media,
+            false,
+            options,
+        )
+        .expect("Failed to create the server");
+        assert_eq!(server.files.list(), vec![1]);
+        assert_eq!(server.media.list(), vec![5]);
+
+        // Both directories are scanned again
+        fs::write(files.join("2.txt"), "Two").unwrap();
+        fs::write(media_directory.join("6.png"), image()).unwrap();
+        let changes = server.reload_content().unwrap();
+        assert_eq!(
+            changes,
+            ContentChanges {
+                added: vec![2, 6],
+                ..ContentChanges::default()
+            }
+        );
+        assert_eq!(server.files.list(), vec![1, 2]);
+        assert_eq!(server.media.list(), vec![5, 6]);
+    }
+
+    #[test]
+    fn hybrid_request_kind_test() {
+        let mut files = MemoryStore::new();
+        files.insert(1, "Hybrid text");
+        let mut media = MemoryStore::new();
+        media.insert(1, image());
+        media.insert(2, image());
+        let (mut server, neighbor) =
+            build_server_with_stores(Box::new(files), Box::new(media), ServerType::Text);
+        let media_details = ContentRequest::Metadata {
+            id: 2,
+            kind: Some(ContentKind::Media),
+        };
+
+        // A text server does not answer about its media directory
+        let response = request_content(&mut server, &neighbor.1, media_details.clone(), 1);
+        assert!(matches!(
+            response,
+            ContentResponse::Error(ContentError::WrongServerType(_))
+        ));
+
+        // A hybrid server picks the files of the requested kind
+        server.options.hybrid = true;
+        let ContentResponse::Metadata(details) =
+            request_content(&mut server, &neighbor.1, media_details, 2)
+        else {
+            panic!("Unexpected response");
+        };
+        assert_eq!((details.id, details.dimensions), (2, Some((4, 4))));
+        let list = ContentRequest::DetailedFileList {
+            kind: Some(ContentKind::Media),
+        };
+        let ContentResponse::DetailedFileList(details) =
+            request_content(&mut server, &neighbor.1, list, 3)
+        else {
+            panic!("Unexpected response");
+        };
+        let ids: Vec<u8> = details.iter().map(|details| details.id).collect();
+        assert_eq!(ids, vec![1, 2]);
+        let range = ContentRequest::Range {
+            id: 1,
+            offset: 0,
+            length: Some(6),
+            kind: None,
+        };
+        let ContentResponse::Range { data, .. } =
+            request_content(&mut server, &neighbor.1, range, 4)
+        else {
+            panic!("Unexpected response");
+        };
+        assert_eq!(data, b"Hybrid");
+
+        // Versions and uploads are only kept for the files of the server type
+        let version = ContentRequest::FileVersion {
+            id: 1,
+            version: None,
+            kind: Some(ContentKind::Media),
+        };
+        let upload = ContentRequest::Upload {
+            data: image(),
+            kind: Some(ContentKind::Media),
+        };
+        for (session_id, rejected) in [(5, version), (6, upload)] {
+            assert!(matches!(
+                request_content(&mut server, &neighbor.1, rejected, session_id),
+                ContentResponse::Error(ContentError::WrongServerType(_))
+            ));
+        }
+        assert_eq!(server.media.list(), vec![1, 2]);
+    }
+}
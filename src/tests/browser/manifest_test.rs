@@ -0,0 +1,68 @@
+#[cfg(test)]
+#[allow(unused)]
+pub mod manifest_test {
+    use std::fs;
+
+    use rustafarian_shared::messages::general_messages::ServerType;
+
+    use crate::content_server::ContentServerOptions;
+    use crate::manifest::Manifest;
+    use crate::messages::{ContentRequest, ContentResponse};
+    use crate::selection::SelectionPolicy;
+    use crate::tests::utils::{build_server_in, request_content, temp_content_dir};
+
+    #[test]
+    fn manifest_toml_test() {
+        let directory = temp_content_dir("manifest_toml");
+        fs::write(
+            directory.join("manifest.toml"),
+            "[[entries]]\nid = 3\ntitle = \"Third\"\ntags = [\"a\", \"b\"]\n",
+        )
+        .unwrap();
+
+        let manifest = Manifest::load(&directory).expect("Manifest not loaded");
+        let entry = manifest.get(3).expect("Missing entry");
+        assert_eq!(entry.title.as_deref(), Some("Third"));
+        assert_eq!(entry.tags, vec!["a", "b"]);
+        assert!(manifest.get(4).is_none());
+    }
+
+    #[test]
+    fn manifest_file_catalog_test() {
+        let directory = temp_content_dir("manifest_catalog");
+        fs::write(directory.join("0001.txt"), "First text").unwrap();
+        fs::write(directory.join("0002.txt"), "Second text").unwrap();
+        fs::write(directory.join("intro.txt"), "Introduction").unwrap();
+        fs::write(
+            directory.join("manifest.json"),
+            r#"{"entries": [
+                {"id": 1, "title": "First", "description": "The first text", "tags": ["intro"]},
+                {"id": 5, "title": "Intro", "mime_type": "text/markdown", "path": "intro.txt"}
+            ]}"#,
+        )
+        .unwrap();
+
+        let options = ContentServerOptions {
+            file_selection: SelectionPolicy::All,
+            ..ContentServerOptions::default()
+        };
+        let (mut server, neighbor) = build_server_in(&directory, ServerType::Text, options);
+        assert_eq!(server.files.list(), vec![1, 2, 5]);
+
+        let request = ContentRequest::FileCatalog { kind: None };
+        let ContentResponse::FileCatalog(entries) =
+            request_content(&mut server, &neighbor.1, request, 5)
+        else {
+            panic!("Unexpected response");
+        };
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].title.as_deref(), Some("First"));
+        assert_eq!(entries[0].description.as_deref(), Some("The first text"));
+        assert_eq!(entries[0].mime_type.as_deref(), Some("text/plain"));
+        assert_eq!(entries[0].tags, vec!["intro"]);
+        assert_eq!(entries[1].title, None);
+        assert_eq!(entries[2].id, 5);
+        assert_eq!(entries[2].mime_type.as_deref(), Some("text/markdown"));
+        assert_eq!(server.files.get(5).unwrap(), b"Introduction");
+    }
+}
@@ -0,0 +1,138 @@
+#[cfg(test)]
+#[allow(unused)]
+pub mod media_encoding_test {
+    use std::io::{Cursor, Read};
+
+    use crossbeam_channel::Receiver;
+    use image::{DynamicImage, ImageFormat, RgbImage};
+    use rustafarian_shared::messages::{
+        browser_messages::{
+            BrowserRequest, BrowserRequestWrapper, BrowserResponse, BrowserResponseWrapper,
+        },
+        general_messages::DroneSend,
+    };
+    use wg_2024::packet::Packet;
+
+    use crate::content_server::ContentServer;
+    use crate::media;
+    use crate::messages::{
+        self, ContentRequestWrapper, ContentResponse, ContentResponseWrapper, MediaEncoding,
+    };
+    use crate::stream::{Payload, ResponseStream};
+    use crate::tests::utils::{media_server_with, receive_message, receive_response, send_request};
+
+    fn stored_image() -> Vec<u8> {
+        // Noise, so the image doesn't compress to a few bytes
+        let image = DynamicImage::ImageRgb8(RgbImage::from_fn(32, 32, |x, y| {
+            image::Rgb([(x * y * 31 % 251) as u8, (x * 17 + y * 5) as u8, 90])
+        }));
+        media::encode(&image, ImageFormat::Png).unwrap()
+    }
+
+    fn build_media_server() -> (ContentServer, Vec<u8>, Receiver<Packet>) {
+        let stored = stored_image();
+        let (mut server, neighbor) = media_server_with(vec![(4, stored.clone())]);
+        server.options.media_passthrough = true;
+        (server, stored, neighbor.1)
+    }
+
+    fn compact_media(response: ContentResponseWrapper) -> (String, Vec<u8>) {
+        let ContentResponseWrapper::Content(ContentResponse::CompactMediaFile {
+            id: 4,
+            mime_type,
+            data,
+            ..
+        }) = response
+        else {
+            panic!("Unexpected response");
+        };
+        (mime_type, messages::decode_media(&data).unwrap())
+    }
+
+    #[test]
+    fn base64_stream_test() {
+        for size in [0, 1, 2, 3, 4, 10_000] {
+            let data: Vec<u8> = (0..=255).cycle().take(size).collect();
+            let expected = ContentResponseWrapper::Content(ContentResponse::CompactMediaFile {
+                id: 4,
+                mime_type: "image/png".to_string(),
+                sha256: None,
+                data: messages::encode_media(&data),
+            })
+            .stringify();
+            let template = ContentResponseWrapper::Content(ContentResponse::CompactMediaFile {
+                id: 4,
+                mime_type: "image/png".to_string(),
+                sha256: None,
+                data: String::new(),
+            })
+            .stringify();
+            let (prefix, suffix) = ResponseStream::split_response(&template, "\"\"").unwrap();
+            let source = Box::new(Cursor::new(data));
+            let mut stream =
+                ResponseStream::new(&prefix, &suffix, Payload::Base64, source).unwrap();
+            assert_eq!(stream.len(), expected.len() as u64);
+
+            let mut serialized = Vec::new();
+            loop {
+                let bytes = stream.next_bytes(128).unwrap();
+                if bytes.is_empty() {
+                    break;
+                }
+                serialized.extend(bytes);
+            }
+            assert_eq!(String::from_utf8(serialized).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn media_encoding_request_test() {
+        let (mut server, stored, neighbor) = build_media_server();
+
+        let media_request = BrowserRequestWrapper::Chat(BrowserRequest::MediaFileRequest(4));
+        send_request(&mut server, &media_request.stringify(), 1);
+        let array_response = receive_message(&neighbor).expect("No response from the server");
+
+        let request = ContentRequestWrapper::AcceptMediaEncoding {
+            encoding: MediaEncoding::Base64,
+            request: media_request.stringify(),
+        };
+        send_request(&mut server, &request.stringify(), 2);
+        let response = receive_response(&neighbor);
+        assert!(response.stringify().len() * 2 < array_response.len());
+        assert_eq!(compact_media(response), ("image/png".to_string(), stored));
+    }
+
+    #[test]
+    fn server_media_encoding_test() {
+        let (mut server, stored, neighbor) = build_media_server();
+        server.options.media_encoding = MediaEncoding::Base64;
+
+        let media_request = BrowserRequestWrapper::Chat(BrowserRequest::MediaFileRequest(4));
+        send_request(&mut server, &media_request.stringify(), 1);
+        assert_eq!(
+            compact_media(receive_response(&neighbor)),
+            ("image/png".to_string(), stored.clone())
+        );
+
+        // A request can still ask for the array of numbers
+        let request = ContentRequestWrapper::AcceptMediaEncoding {
+            encoding: MediaEncoding::Array,
+            request: media_request.stringify(),
+        };
+        send_request(&mut server, &request.stringify(), 2);
+        let response = receive_message(&neighbor).expect("No response from the server");
+        match BrowserResponseWrapper::from_string(String::from_utf8(response).unwrap()) {
+            Ok(BrowserResponseWrapper::Chat(BrowserResponse::MediaFile(4, data))) => {
+                assert_eq!(data, stored);
+            }
+            _ => panic!("Unexpected response"),
+        }
+
+        // Big files are streamed in base64 too
+        server.options.stream_threshold_bytes = 1;
+        server.options.stream_window = 1000;
+        send_request(&mut server, &media_request.stringify(), 3);
+        assert_eq!(compact_media(receive_response(&neighbor)).1, stored);
+    }
+}
@@ -0,0 +1,76 @@
+#[cfg(test)]
+#[allow(unused)]
+pub mod media_format_test {
+    use image::{DynamicImage, ImageFormat, RgbImage};
+    use rustafarian_shared::messages::{
+        browser_messages::{
+            BrowserRequest, BrowserRequestWrapper, BrowserResponse, BrowserResponseWrapper,
+        },
+        general_messages::DroneSend,
+    };
+
+    use crate::media;
+    use crate::messages::{
+        ContentRequest, ContentRequestWrapper, ContentResponse, ContentResponseWrapper,
+    };
+    use crate::tests::utils::{media_server_with, receive_message, receive_response, send_request};
+
+    fn png_image() -> Vec<u8> {
+        let image = DynamicImage::ImageRgb8(RgbImage::from_fn(8, 8, |x, y| {
+            image::Rgb([(x * 30) as u8, (y * 30) as u8, 120])
+        }));
+        media::encode(&image, ImageFormat::Png).unwrap()
+    }
+
+    #[test]
+    fn media_original_format_test() {
+        let (mut server, neighbor) = media_server_with(vec![(9, png_image())]);
+
+        let request = BrowserRequestWrapper::Chat(BrowserRequest::MediaFileRequest(9));
+        send_request(&mut server, &request.stringify(), 6);
+
+        let response = receive_message(&neighbor.1).expect("No response from the server");
+        let response: BrowserResponseWrapper =
+            serde_json::from_slice(&response).expect("Error deserializing the response");
+        match response {
+            BrowserResponseWrapper::Chat(BrowserResponse::MediaFile(id, data)) => {
+                assert_eq!(id, 9);
+                assert_eq!(image::guess_format(&data).unwrap(), ImageFormat::Png);
+            }
+            _ => panic!("Unexpected response"),
+        }
+    }
+
+    #[test]
+    fn media_negotiated_format_test() {
+        let (mut server, neighbor) = media_server_with(vec![(9, png_image())]);
+
+        let request = ContentRequestWrapper::Content(ContentRequest::MediaFile {
+            id: 9,
+            format: Some("image/jpeg".to_string()),
+        });
+        send_request(&mut server, &request.stringify(), 7);
+
+        let response = receive_response(&neighbor.1);
+        let ContentResponseWrapper::Content(ContentResponse::MediaFile {
+            id,
+            mime_type,
+            data,
+            ..
+        }) = response
+        else {
+            panic!("Unexpected response");
+        };
+        assert_eq!(id, 9);
+        assert_eq!(mime_type, "image/jpeg");
+        assert_eq!(image::guess_format(&data).unwrap(), ImageFormat::Jpeg);
+    }
+
+    #[test]
+    fn readable_extensions_test() {
+        let extensions = media::readable_extensions();
+        for extension in ["jpg", "png", "gif", "webp"] {
+            assert!(extensions.contains(&extension), "Missing {extension}");
+        }
+    }
+}
@@ -0,0 +1,75 @@
+#[cfg(test)]
+#[allow(unused)]
+pub mod media_passthrough_test {
+    use image::{DynamicImage, ImageFormat, RgbImage};
+    use rustafarian_shared::messages::{
+        browser_messages::{
+            BrowserRequest, BrowserRequestWrapper, BrowserResponse, BrowserResponseWrapper,
+        },
+        general_messages::DroneSend,
+    };
+
+    use crate::content_store::{sha256_hex, ContentStore, MemoryStore};
+    use crate::media;
+    use crate::messages::{
+        ContentRequest, ContentRequestWrapper, ContentResponse, ContentResponseWrapper,
+    };
+    use crate::tests::utils::{media_server_with, receive_response, send_request};
+
+    fn stored_image() -> Vec<u8> {
+        let image = DynamicImage::ImageRgb8(RgbImage::from_fn(16, 16, |x, y| {
+            image::Rgb([(x * 16) as u8, (y * 16) as u8, 60])
+        }));
+        media::encode(&image, ImageFormat::Jpeg).unwrap()
+    }
+
+    fn request_media(format: Option<&str>) -> (Vec<u8>, String, Option<String>) {
+        let (mut server, neighbor) = media_server_with(vec![(4, stored_image())]);
+        server.options.media_passthrough = true;
+
+        let request = ContentRequestWrapper::Content(ContentRequest::MediaFile {
+            id: 4,
+            format: format.map(str::to_string),
+        });
+        send_request(&mut server, &request.stringify(), 8);
+
+        let response = receive_response(&neighbor.1);
+        let ContentResponseWrapper::Content(ContentResponse::MediaFile {
+            mime_type,
+            sha256,
+            data,
+            ..
+        }) = response
+        else {
+            panic!("Unexpected response");
+        };
+        (data, mime_type, sha256)
+    }
+
+    #[test]
+    fn media_passthrough_test() {
+        let stored = stored_image();
+        let (data, mime_type, sha256) = request_media(None);
+        assert_eq!(data, stored);
+        assert_eq!(mime_type, "image/jpeg");
+        assert_eq!(sha256, Some(sha256_hex(&stored)));
+    }
+
+    #[test]
+    fn media_passthrough_conversion_test() {
+        let (data, mime_type, sha256) = request_media(Some("png"));
+        assert_eq!(image::guess_format(&data).unwrap(), ImageFormat::Png);
+        assert_eq!(mime_type, "image/png");
+        assert_eq!(sha256, None);
+    }
+
+    #[test]
+    fn digest_computed_on_load_test() {
+        let mut store = MemoryStore::new();
+        store.insert(1, "abc");
+        assert_eq!(
+            store.metadata(1).unwrap().sha256.as_deref(),
+            Some("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad")
+        );
+    }
+}
@@ -0,0 +1,77 @@
+#[cfg(test)]
+#[allow(unused)]
+pub mod media_references_test {
+    use rustafarian_shared::messages::general_messages::ServerType;
+
+    use crate::content_store::{ContentStore, MemoryStore};
+    use crate::links::media_references;
+    use crate::messages::{
+        ContentError, ContentRequest, ContentRequestWrapper, ContentResponse,
+        ContentResponseWrapper,
+    };
+    use crate::tests::utils::{
+        build_server_with_stores, receive_response, request_content, send_request,
+    };
+
+    #[test]
+    fn media_references_parse_test() {
+        let text = "# Gallery\n\
+            [media:12] and again [media:12]\n\
+            ![A cat](media:3) ![A dog](7.png \"Dog\") ![Remote](https://example.com/1.png)\n\
+            [a link](5.png) [media:300] [media:abc] ![Broken](cat.png)";
+        assert_eq!(media_references(text), vec![12, 3, 7]);
+        assert!(media_references("no links [here").is_empty());
+    }
+
+    #[test]
+    fn media_references_request_test() {
+        let mut files = MemoryStore::new();
+        files.insert(1, "Look at ![this](media:4) and [media:2]");
+        files.index_media_references();
+        assert_eq!(
+            files.metadata(1).unwrap().media_references,
+            Some(vec![4, 2])
+        );
+        // Not indexed, parsed when requested
+        files.insert(2, b"Caf\xe9 [media:9]".to_vec());
+        let (mut server, neighbor) = build_server_with_stores(
+            Box::new(files),
+            Box::new(MemoryStore::new()),
+            ServerType::Text,
+        );
+
+        for (id, expected) in [(1, vec![4, 2]), (2, vec![9])] {
+            let request = ContentRequest::MediaReferences(id);
+            let ContentResponse::MediaReferences {
+                id: response_id,
+                media,
+            } = request_content(&mut server, &neighbor.1, request, u64::from(id))
+            else {
+                panic!("Unexpected response");
+            };
+            assert_eq!(response_id, id);
+            assert_eq!(media, expected);
+        }
+    }
+
+    #[test]
+    fn media_references_wrong_server_test() {
+        let mut media = MemoryStore::new();
+        media.insert(1, "[media:2]");
+        let (mut server, neighbor) = build_server_with_stores(
+            Box::new(MemoryStore::new()),
+            Box::new(media),
+            ServerType::Media,
+        );
+
+        let request = ContentRequestWrapper::Content(ContentRequest::MediaReferences(1));
+        send_request(&mut server, &request.stringify(), 1);
+        let response = receive_response(&neighbor.1);
+        assert!(matches!(
+            response,
+            ContentResponseWrapper::Content(ContentResponse::Error(ContentError::WrongServerType(
+                _
+            )))
+        ));
+    }
+}
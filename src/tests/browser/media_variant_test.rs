@@ -0,0 +1,116 @@
+#[cfg(test)]
+#[allow(unused)]
+pub mod media_variant_test {
+    use image::{DynamicImage, GenericImageView, ImageFormat, RgbaImage};
+
+    use crate::media;
+    use crate::messages::{
+        ContentRequest, ContentRequestWrapper, ContentResponse, ContentResponseWrapper,
+    };
+    use crate::response_cache::ResponseKey;
+    use crate::tests::utils::{media_server_with, receive_response, send_request};
+    use crate::variants::VariantKey;
+
+    fn wide_image() -> Vec<u8> {
+        let image = DynamicImage::ImageRgba8(RgbaImage::from_fn(400, 200, |x, y| {
+            image::Rgba([(x % 256) as u8, (y % 256) as u8, 90, 255])
+        }));
+        media::encode(&image, ImageFormat::Png).unwrap()
+    }
+
+    fn receive_variant(
+        neighbor: &crossbeam_channel::Receiver<wg_2024::packet::Packet>,
+    ) -> (u32, u32, Vec<u8>) {
+        let ContentResponseWrapper::Content(ContentResponse::MediaVariant {
+            width,
+            height,
+            data,
+            ..
+        }) = receive_response(neighbor)
+        else {
+            panic!("Unexpected response");
+        };
+        (width, height, data)
+    }
+
+    #[test]
+    fn thumbnail_test() {
+        let (mut server, neighbor) = media_server_with(vec![(2, wide_image())]);
+        server.options.thumbnail_dimension = 50;
+
+        let request = ContentRequestWrapper::Content(ContentRequest::Thumbnail(2));
+        send_request(&mut server, &request.stringify(), 9);
+
+        let (width, height, data) = receive_variant(&neighbor.1);
+        assert_eq!((width, height), (50, 25));
+        let decoded = image::load_from_memory(&data).unwrap();
+        assert_eq!(decoded.dimensions(), (50, 25));
+        assert_eq!(image::guess_format(&data).unwrap(), ImageFormat::Jpeg);
+    }
+
+    #[test]
+    fn variant_cached_test() {
+        let (mut server, neighbor) = media_server_with(vec![(2, wide_image())]);
+
+        let request = ContentRequestWrapper::Content(ContentRequest::MediaVariant {
+            id: 2,
+            max_dimension: 96,
+            quality: 40,
+        });
+        send_request(&mut server, &request.stringify(), 10);
+        let first = receive_variant(&neighbor.1);
+        send_request(&mut server, &request.stringify(), 11);
+        let second = receive_variant(&neighbor.1);
+
+        assert_eq!(first, second);
+        assert_eq!((first.0, first.1), (96, 48));
+        assert_eq!(server.responses.len(), 1);
+        assert_eq!(server.responses.hits(), 1);
+        let key = VariantKey {
+            id: 2,
+            max_dimension: 96,
+            quality: 40,
+        };
+        assert!(server
+            .responses
+            .peek_len(&ResponseKey::MediaVariant(key))
+            .is_some());
+    }
+
+    #[test]
+    fn variant_quantized_test() {
+        let (mut server, neighbor) = media_server_with(vec![(2, wide_image())]);
+        server.options.max_variant_dimension = 64;
+
+        // Close dimensions and qualities share the same rendition
+        for (session_id, (max_dimension, quality)) in [(33, 37), (48, 43)].into_iter().enumerate() {
+            let request = ContentRequestWrapper::Content(ContentRequest::MediaVariant {
+                id: 2,
+                max_dimension,
+                quality,
+            });
+            send_request(&mut server, &request.stringify(), session_id as u64);
+            let (width, height, _) = receive_variant(&neighbor.1);
+            assert_eq!((width, height), (48, 24));
+        }
+        assert_eq!(server.responses.len(), 1);
+
+        // Dimensions are capped at the configured maximum
+        let request = ContentRequestWrapper::Content(ContentRequest::MediaVariant {
+            id: 2,
+            max_dimension: u32::MAX,
+            quality: 255,
+        });
+        send_request(&mut server, &request.stringify(), 3);
+        let (width, height, _) = receive_variant(&neighbor.1);
+        assert_eq!((width, height), (64, 32));
+        assert_eq!(
+            VariantKey::quantized(2, u32::MAX, 255, 64),
+            VariantKey {
+                id: 2,
+                max_dimension: 64,
+                quality: 100,
+            }
+        );
+    }
+}
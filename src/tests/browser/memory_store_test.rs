@@ -0,0 +1,73 @@
+#[cfg(test)]
+#[allow(unused)]
+pub mod memory_store_test {
+    use rustafarian_shared::messages::{
+        browser_messages::{
+            BrowserRequest, BrowserRequestWrapper, BrowserResponse, BrowserResponseWrapper,
+        },
+        general_messages::{DroneSend, ServerType},
+    };
+
+    use crate::content_store::{ContentStore, MemoryStore};
+    use crate::tests::utils::{build_server_with_stores, receive_message, send_request};
+
+    #[test]
+    fn memory_store_text_request_test() {
+        let mut files = MemoryStore::new();
+        files.insert(7, "Text served from memory");
+        let (mut server, neighbor) = build_server_with_stores(
+            Box::new(files),
+            Box::new(MemoryStore::new()),
+            ServerType::Text,
+        );
+
+        let request = BrowserRequestWrapper::Chat(BrowserRequest::TextFileRequest(7));
+        send_request(&mut server, &request.stringify(), 3);
+
+        let response = receive_message(&neighbor.1).expect("No response from the server");
+        let response: BrowserResponseWrapper =
+            serde_json::from_slice(&response).expect("Error deserializing the response");
+        match response {
+            BrowserResponseWrapper::Chat(BrowserResponse::TextFile(id, content)) => {
+                assert_eq!(id, 7);
+                assert_eq!(content, "Text served from memory");
+            }
+            _ => panic!("Unexpected response"),
+        }
+    }
+
+    #[test]
+    fn memory_store_file_list_test() {
+        let mut files = MemoryStore::new();
+        files.insert(3, "three");
+        files.insert(1, "one");
+        let (mut server, neighbor) = build_server_with_stores(
+            Box::new(files),
+            Box::new(MemoryStore::new()),
+            ServerType::Text,
+        );
+
+        let request = BrowserRequestWrapper::Chat(BrowserRequest::FileList);
+        send_request(&mut server, &request.stringify(), 4);
+
+        let response = receive_message(&neighbor.1).expect("No response from the server");
+        let response: BrowserResponseWrapper =
+            serde_json::from_slice(&response).expect("Error deserializing the response");
+        match response {
+            BrowserResponseWrapper::Chat(BrowserResponse::FileList(ids)) => {
+                assert_eq!(ids, vec![1, 3]);
+            }
+            _ => panic!("Unexpected response"),
+        }
+    }
+
+    #[test]
+    fn memory_store_missing_id_test() {
+        let store = MemoryStore::new();
+        assert!(!store.contains(5));
+        assert_eq!(
+            store.get(5).unwrap_err().kind(),
+            std::io::ErrorKind::NotFound
+        );
+    }
+}
@@ -0,0 +1,127 @@
+#[cfg(test)]
+#[allow(unused)]
+pub mod metadata_test {
+    use image::{DynamicImage, ImageFormat, RgbImage};
+    use rustafarian_shared::messages::{
+        browser_messages::{BrowserResponse, BrowserResponseWrapper},
+        general_messages::{DroneSend, ServerType},
+    };
+    use wg_2024::packet::FRAGMENT_DSIZE;
+
+    use crate::content_store::{ContentStore, MemoryStore};
+    use crate::media;
+    use crate::messages::{
+        self, ContentError, ContentRequest, ContentResponse, ContentResponseWrapper, MediaEncoding,
+    };
+    use crate::tests::utils::{build_server_with_stores, request_content};
+
+    #[test]
+    fn metadata_media_test() {
+        let image = DynamicImage::ImageRgb8(RgbImage::new(8, 6));
+        let png = media::encode(&image, ImageFormat::Png).unwrap();
+        let mut media_store = MemoryStore::new();
+        media_store.insert(9, png.clone());
+        let (mut server, neighbor) = build_server_with_stores(
+            Box::new(MemoryStore::new()),
+            Box::new(media_store),
+            ServerType::Media,
+        );
+
+        let request = ContentRequest::Metadata { id: 9, kind: None };
+        let ContentResponse::Metadata(details) =
+            request_content(&mut server, &neighbor.1, request, 1)
+        else {
+            panic!("Unexpected response");
+        };
+        let media_response =
+            BrowserResponseWrapper::Chat(BrowserResponse::MediaFile(9, png.clone()));
+        assert_eq!(details.id, 9);
+        assert_eq!(details.size, png.len() as u64);
+        assert_eq!(details.dimensions, Some((8, 6)));
+        assert!(details.modified.is_some());
+        assert_eq!(
+            details.fragments as usize,
+            media_response.stringify().len().div_ceil(FRAGMENT_DSIZE)
+        );
+    }
+
+    #[test]
+    fn metadata_fragments_estimate_test() {
+        let data: Vec<u8> = (0..=255).cycle().take(256 * 8).collect();
+        let mut media_store = MemoryStore::new();
+        media_store.insert(3, data.clone());
+        let (mut server, neighbor) = build_server_with_stores(
+            Box::new(MemoryStore::new()),
+            Box::new(media_store),
+            ServerType::Media,
+        );
+        let fragments = |server: &mut crate::content_server::ContentServer, session_id| {
+            let request = ContentRequest::Metadata { id: 3, kind: None };
+            let ContentResponse::Metadata(details) =
+                request_content(server, &neighbor.1, request, session_id)
+            else {
+                panic!("Unexpected response");
+            };
+            details.fragments as usize
+        };
+
+        // The estimate follows the encoding of the media responses
+        let array = BrowserResponseWrapper::Chat(BrowserResponse::MediaFile(3, data.clone()));
+        assert_eq!(
+            fragments(&mut server, 1),
+            array.stringify().len().div_ceil(FRAGMENT_DSIZE)
+        );
+        server.options.media_encoding = MediaEncoding::Base64;
+        let metadata = server.media.metadata(3).unwrap();
+        let compact = ContentResponseWrapper::Content(ContentResponse::CompactMediaFile {
+            id: 3,
+            mime_type: "application/octet-stream".to_string(),
+            sha256: metadata.sha256,
+            data: messages::encode_media(&data),
+        });
+        assert_eq!(
+            fragments(&mut server, 2),
+            compact.stringify().len().div_ceil(FRAGMENT_DSIZE)
+        );
+    }
+
+    #[test]
+    fn metadata_detailed_list_test() {
+        let mut files = MemoryStore::new();
+        files.insert(1, "x".repeat(300));
+        files.insert(2, "Short");
+        let (mut server, neighbor) = build_server_with_stores(
+            Box::new(files),
+            Box::new(MemoryStore::new()),
+            ServerType::Text,
+        );
+
+        let request = ContentRequest::DetailedFileList { kind: None };
+        let ContentResponse::DetailedFileList(details) =
+            request_content(&mut server, &neighbor.1, request, 2)
+        else {
+            panic!("Unexpected response");
+        };
+        assert_eq!(details.len(), 2);
+        assert_eq!((details[0].id, details[0].size), (1, 300));
+        assert_eq!(details[0].fragments, 3);
+        assert_eq!((details[1].id, details[1].size), (2, 5));
+        assert_eq!(details[1].fragments, 1);
+        assert_eq!(details[1].dimensions, None);
+    }
+
+    #[test]
+    fn metadata_missing_file_test() {
+        let (mut server, neighbor) = build_server_with_stores(
+            Box::new(MemoryStore::new()),
+            Box::new(MemoryStore::new()),
+            ServerType::Text,
+        );
+
+        let request = ContentRequest::Metadata { id: 4, kind: None };
+        assert_eq!(
+            request_content(&mut server, &neighbor.1, request, 3),
+            ContentResponse::Error(ContentError::NotFound(4))
+        );
+    }
+}
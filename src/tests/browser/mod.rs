@@ -1,4 +1,8 @@
 pub mod add_sender_test;
+pub mod chat_server_test;
+pub mod compression_test;
+pub mod config_test;
+pub mod error_response_test;
 pub mod error_routing_test;
 pub mod file_list_request_test;
 pub mod file_media_request_test;
@@ -7,6 +11,27 @@ pub mod flood_request_test;
 pub mod flood_request_twice_test;
 pub mod flood_response_test;
 pub mod fragment_dropped_test;
+pub mod hot_reload_test;
+pub mod hybrid_server_test;
+pub mod manifest_test;
+pub mod media_encoding_test;
+pub mod media_format_test;
+pub mod media_passthrough_test;
+pub mod media_references_test;
+pub mod media_variant_test;
+pub mod memory_store_test;
+pub mod metadata_test;
+pub mod range_request_test;
 pub mod remove_sender_test;
+pub mod response_cache_test;
+pub mod search_test;
+pub mod seeded_server_test;
+pub mod selection_policy_test;
+pub mod server_error_test;
 pub mod server_type_request_test;
 pub mod server_type_test;
+pub mod simulation_test;
+pub mod streaming_test;
+pub mod text_encoding_test;
+pub mod upload_test;
+pub mod versioning_test;
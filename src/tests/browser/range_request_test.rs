@@ -0,0 +1,91 @@
+#[cfg(test)]
+#[allow(unused)]
+pub mod range_request_test {
+    use std::collections::HashMap;
+    use std::fs;
+    use std::io::ErrorKind;
+
+    use rustafarian_shared::messages::general_messages::ServerType;
+
+    use crate::content_store::{ContentStore, FileSystemStore, MemoryStore};
+    use crate::messages::{
+        ContentError, ContentRequest, ContentRequestWrapper, ContentResponse,
+        ContentResponseWrapper,
+    };
+    use crate::tests::utils::{
+        build_server_with_stores, receive_response, send_request, temp_content_dir,
+    };
+
+    #[test]
+    fn range_store_test() {
+        let directory = temp_content_dir("range_store");
+        let path = directory.join("1.txt");
+        fs::write(&path, "0123456789").unwrap();
+        let disk = FileSystemStore::new(HashMap::from([(1, path)]));
+        let mut memory = MemoryStore::new();
+        memory.insert(1, "0123456789");
+
+        for store in [&disk as &dyn ContentStore, &memory] {
+            assert_eq!(
+                store.get_range(1, 2, Some(3)).unwrap(),
+                (b"234".to_vec(), 10)
+            );
+            assert_eq!(store.get_range(1, 0, Some(4)).unwrap().0, b"0123");
+            assert_eq!(store.get_range(1, 7, None).unwrap().0, b"789");
+            assert_eq!(store.get_range(1, 8, Some(100)).unwrap().0, b"89");
+            assert!(store.get_range(1, 10, None).unwrap().0.is_empty());
+            assert_eq!(
+                store.get_range(1, 11, None).unwrap_err().kind(),
+                ErrorKind::InvalidInput
+            );
+            assert_eq!(
+                store.get_range(2, 0, None).unwrap_err().kind(),
+                ErrorKind::NotFound
+            );
+        }
+    }
+
+    #[test]
+    fn range_request_test() {
+        let mut files = MemoryStore::new();
+        files.insert(3, "A long document split in pages");
+        let (mut server, neighbor) = build_server_with_stores(
+            Box::new(files),
+            Box::new(MemoryStore::new()),
+            ServerType::Text,
+        );
+
+        let request = ContentRequestWrapper::Content(ContentRequest::Range {
+            id: 3,
+            offset: 7,
+            length: Some(8),
+            kind: None,
+        });
+        send_request(&mut server, &request.stringify(), 1);
+
+        let response = receive_response(&neighbor.1);
+        assert_eq!(
+            response,
+            ContentResponseWrapper::Content(ContentResponse::Range {
+                id: 3,
+                offset: 7,
+                total_size: 30,
+                data: b"document".to_vec(),
+            })
+        );
+
+        // A range past the end is not served
+        let request = ContentRequestWrapper::Content(ContentRequest::Range {
+            id: 3,
+            offset: 31,
+            length: None,
+            kind: None,
+        });
+        send_request(&mut server, &request.stringify(), 2);
+        let response = receive_response(&neighbor.1);
+        assert!(matches!(
+            response,
+            ContentResponseWrapper::Content(ContentResponse::Error(ContentError::BadRequest(_)))
+        ));
+    }
+}
@@ -0,0 +1,75 @@
+#[cfg(test)]
+#[allow(unused)]
+pub mod response_cache_test {
+    use rustafarian_shared::messages::{
+        browser_messages::{
+            BrowserRequest, BrowserRequestWrapper, BrowserResponse, BrowserResponseWrapper,
+        },
+        general_messages::{DroneSend, ServerType},
+    };
+
+    use crate::content_store::MemoryStore;
+    use crate::response_cache::{ResponseCache, ResponseKey};
+    use crate::tests::utils::{build_server_with_stores, receive_message, send_request};
+
+    #[test]
+    fn response_cache_eviction_test() {
+        let mut cache = ResponseCache::new(10);
+        cache.insert(ResponseKey::TextFile(1), "aaaa".to_string());
+        cache.insert(ResponseKey::TextFile(2), "bbbb".to_string());
+        // Use the first response so the second one is the least recently used
+        assert_eq!(
+            cache.get(&ResponseKey::TextFile(1)).as_deref(),
+            Some("aaaa")
+        );
+        cache.insert(ResponseKey::MediaFile(3), "cccc".to_string());
+
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.size(), 8);
+        assert!(cache.get(&ResponseKey::TextFile(2)).is_none());
+        assert!(cache.get(&ResponseKey::MediaFile(3)).is_some());
+        assert_eq!((cache.hits(), cache.misses()), (2, 1));
+
+        // Too big to be cached
+        cache.insert(ResponseKey::TextFile(4), "x".repeat(11));
+        assert!(cache.get(&ResponseKey::TextFile(4)).is_none());
+
+        cache.invalidate(3);
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.size(), 4);
+    }
+
+    #[test]
+    fn response_cache_text_request_test() {
+        let mut files = MemoryStore::new();
+        files.insert(5, "Popular text");
+        let (mut server, neighbor) = build_server_with_stores(
+            Box::new(files),
+            Box::new(MemoryStore::new()),
+            ServerType::Text,
+        );
+
+        for session_id in 1..=3 {
+            let request = BrowserRequestWrapper::Chat(BrowserRequest::TextFileRequest(5));
+            send_request(&mut server, &request.stringify(), session_id);
+
+            let response = receive_message(&neighbor.1).expect("No response from the server");
+            let response: BrowserResponseWrapper =
+                serde_json::from_slice(&response).expect("Error deserializing the response");
+            match response {
+                BrowserResponseWrapper::Chat(BrowserResponse::TextFile(id, content)) => {
+                    assert_eq!(id, 5);
+                    assert_eq!(content, "Popular text");
+                }
+                _ => panic!("Unexpected response"),
+            }
+            if session_id == 2 {
+                server.invalidate_content(5);
+            }
+        }
+        // The second request is served from the cache, the third one after the invalidation is not
+        assert_eq!(server.responses.hits(), 1);
+        assert_eq!(server.responses.misses(), 2);
+        assert_eq!(server.responses.len(), 1);
+    }
+}
@@ -0,0 +1,107 @@
+#[cfg(test)]
+#[allow(unused)]
+pub mod search_test {
+    use rustafarian_shared::messages::general_messages::ServerType;
+
+    use crate::content_store::MemoryStore;
+    use crate::messages::{
+        ContentRequest, ContentRequestWrapper, ContentResponse, ContentResponseWrapper,
+    };
+    use crate::search::{SearchHit, SearchIndex};
+    use crate::tests::utils::{build_server_with_stores, receive_response, send_request};
+
+    #[test]
+    fn search_ranking_test() {
+        let mut index = SearchIndex::default();
+        index.insert(
+            1,
+            "Drones route packets. Packets are fragmented.".to_string(),
+        );
+        index.insert(2, "The server answers packets".to_string());
+        index.insert(3, "Nothing to see here".to_string());
+
+        let hits = index.search("PACKETS drones", 10);
+        let ids: Vec<u8> = hits.iter().map(|hit| hit.id).collect();
+        assert_eq!(ids, vec![1, 2]);
+        assert_eq!(hits[1].snippet, "The server answers packets");
+        assert_eq!(index.search("packets", 1).len(), 1);
+        assert!(index.search("missing", 10).is_empty());
+
+        index.remove(1);
+        assert_eq!(index.search("drones", 10), Vec::<SearchHit>::new());
+    }
+
+    #[test]
+    fn search_snippet_test() {
+        let mut index = SearchIndex::default();
+        let text = format!("{} needle {}", "hay ".repeat(30), "straw ".repeat(30));
+        index.insert(1, text);
+
+        let hits = index.search("needle", 10);
+        let snippet = &hits[0].snippet;
+        assert!(snippet.starts_with('…'));
+        assert!(snippet.ends_with('…'));
+        assert!(snippet.contains("hay needle straw"));
+    }
+
+    #[test]
+    fn search_lines_test() {
+        let mut index = SearchIndex::default();
+        let text = "Drones route\npackets\n";
+        index
+            .insert_lines(1, |lines| {
+                text.split_inclusive('\n').for_each(lines);
+                Ok(())
+            })
+            .unwrap();
+
+        // The file is found but its text is not kept for the snippet
+        let hits = index.search("packets", 10);
+        assert_eq!(
+            hits,
+            vec![SearchHit {
+                id: 1,
+                snippet: String::new(),
+            }]
+        );
+
+        let failed = index.insert_lines(1, |lines| {
+            lines("Half read");
+            Err(std::io::ErrorKind::UnexpectedEof.into())
+        });
+        assert!(failed.is_err());
+        assert!(index.search("drones half", 10).is_empty());
+    }
+
+    #[test]
+    fn search_request_test() {
+        let mut files = MemoryStore::new();
+        files.insert(4, "Rustafarian browser");
+        files.insert(5, b"Caf\xe9 au lait".to_vec());
+        let (mut server, neighbor) = build_server_with_stores(
+            Box::new(files),
+            Box::new(MemoryStore::new()),
+            ServerType::Text,
+        );
+
+        let request = ContentRequestWrapper::Content(ContentRequest::Search {
+            query: "café".to_string(),
+        });
+        send_request(&mut server, &request.stringify(), 1);
+
+        let response = receive_response(&neighbor.1);
+        let ContentResponseWrapper::Content(ContentResponse::SearchResults { query, hits }) =
+            response
+        else {
+            panic!("Unexpected response");
+        };
+        assert_eq!(query, "café");
+        assert_eq!(
+            hits,
+            vec![SearchHit {
+                id: 5,
+                snippet: "Café au lait".to_string(),
+            }]
+        );
+    }
+}
@@ -0,0 +1,71 @@
+#[cfg(test)]
+#[allow(unused)]
+pub mod seeded_server_test {
+    use std::collections::HashMap;
+
+    use crossbeam_channel::{unbounded, Receiver};
+    use rustafarian_shared::messages::{
+        commander_messages::SimControllerResponseWrapper, general_messages::ServerType,
+    };
+    use wg_2024::packet::{Packet, PacketType};
+
+    use crate::content_server::{ContentServer, ContentServerOptions};
+
+    fn build_seeded_server(
+        seed: u64,
+    ) -> (
+        ContentServer,
+        Receiver<Packet>,
+        Receiver<SimControllerResponseWrapper>,
+    ) {
+        let neighbor = unbounded();
+        let controller = unbounded();
+        let mut neighbors = HashMap::new();
+        neighbors.insert(2, neighbor.0);
+        let options = ContentServerOptions {
+            seed: Some(seed),
+            ..ContentServerOptions::default()
+        };
+        let server = ContentServer::with_options(
+            1,
+            neighbors,
+            unbounded().1,
+            unbounded().1,
+            controller.0,
+            "files",
+            "media",
+            ServerType::Text,
+            false,
+            options,
+        )
+        .expect("Failed to create the server");
+        (server, neighbor.1, controller.1)
+    }
+
+    fn flood_ids(server: &mut ContentServer, neighbor: &Receiver<Packet>) -> (u64, u64) {
+        server.send_flood_request();
+        let packet = neighbor.try_recv().expect("No flood request sent");
+        match packet.pack_type {
+            PacketType::FloodRequest(request) => (request.flood_id, packet.session_id),
+            _ => panic!("The packet sent is not a flood request"),
+        }
+    }
+
+    #[test]
+    fn seeded_selection_test() {
+        let (first, _, _) = build_seeded_server(42);
+        let (second, _, _) = build_seeded_server(42);
+        assert_eq!(first.files.list().len(), 10);
+        assert_eq!(first.files.list(), second.files.list());
+    }
+
+    #[test]
+    fn seeded_flood_request_test() {
+        let (mut first, first_neighbor, _first_controller) = build_seeded_server(7);
+        let (mut second, second_neighbor, _second_controller) = build_seeded_server(7);
+        assert_eq!(
+            flood_ids(&mut first, &first_neighbor),
+            flood_ids(&mut second, &second_neighbor)
+        );
+    }
+}
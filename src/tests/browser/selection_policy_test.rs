@@ -0,0 +1,122 @@
+#[cfg(test)]
+#[allow(unused)]
+pub mod selection_policy_test {
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+
+    use crossbeam_channel::unbounded;
+    use rustafarian_shared::messages::general_messages::ServerType;
+
+    use crate::content_server::{ContentServer, ContentServerOptions};
+    use crate::error::ContentServerError;
+    use crate::selection::SelectionPolicy;
+
+    fn candidates() -> Vec<(u8, PathBuf)> {
+        vec![
+            (4, PathBuf::from("files/0004.txt")),
+            (1, PathBuf::from("files/0001.txt")),
+            (12, PathBuf::from("files/0012.txt")),
+            (3, PathBuf::from("files/0003.txt")),
+        ]
+    }
+
+    fn ids(selected: &[(u8, PathBuf)]) -> Vec<u8> {
+        selected.iter().map(|(id, _)| *id).collect()
+    }
+
+    #[test]
+    fn selection_all_test() {
+        assert_eq!(
+            ids(&SelectionPolicy::All.apply(candidates(), &mut rand::thread_rng())),
+            vec![1, 3, 4, 12]
+        );
+    }
+
+    #[test]
+    fn selection_first_sorted_test() {
+        assert_eq!(
+            ids(&SelectionPolicy::FirstSorted(2).apply(candidates(), &mut rand::thread_rng())),
+            vec![1, 3]
+        );
+    }
+
+    #[test]
+    fn selection_random_test() {
+        let selected = SelectionPolicy::Random(3).apply(candidates(), &mut rand::thread_rng());
+        assert_eq!(selected.len(), 3);
+        assert!(ids(&selected).iter().all(|id| [1, 3, 4, 12].contains(id)));
+    }
+
+    #[test]
+    fn selection_ids_test() {
+        assert_eq!(
+            ids(&SelectionPolicy::Ids(vec![12, 4, 99]).apply(candidates(), &mut rand::thread_rng())),
+            vec![4, 12]
+        );
+    }
+
+    #[test]
+    fn selection_glob_test() {
+        let policy = SelectionPolicy::Glob {
+            include: vec!["000*.txt".to_string()],
+            exclude: vec!["0003.*".to_string()],
+        };
+        assert_eq!(
+            ids(&policy.apply(candidates(), &mut rand::thread_rng())),
+            vec![1, 4]
+        );
+    }
+
+    #[test]
+    fn selection_invalid_glob_test() {
+        let policy = SelectionPolicy::Glob {
+            include: vec!["[".to_string()],
+            exclude: Vec::new(),
+        };
+        // Nothing is selected rather than every file
+        assert!(policy
+            .apply(candidates(), &mut rand::thread_rng())
+            .is_empty());
+
+        // The server refuses the policy
+        let options = ContentServerOptions {
+            file_selection: policy,
+            ..ContentServerOptions::default()
+        };
+        let result = ContentServer::with_options(
+            1,
+            HashMap::new(),
+            unbounded().1,
+            unbounded().1,
+            unbounded().0,
+            "files",
+            "media",
+            ServerType::Text,
+            false,
+            options,
+        );
+        assert!(matches!(result, Err(ContentServerError::InvalidConfig(_))));
+    }
+
+    #[test]
+    fn server_selection_test() {
+        let options = ContentServerOptions {
+            file_selection: SelectionPolicy::FirstSorted(3),
+            ..ContentServerOptions::default()
+        };
+        let server = ContentServer::with_options(
+            1,
+            HashMap::new(),
+            unbounded().1,
+            unbounded().1,
+            unbounded().0,
+            "files",
+            "media",
+            ServerType::Text,
+            false,
+            options,
+        )
+        .expect("Failed to create the server");
+        assert_eq!(server.files.list(), vec![1, 2, 3]);
+    }
+}
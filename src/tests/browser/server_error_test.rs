@@ -0,0 +1,124 @@
+#[cfg(test)]
+#[allow(unused)]
+pub mod server_error_test {
+    use std::collections::HashMap;
+    use std::fs;
+    use std::path::Path;
+
+    use crossbeam_channel::unbounded;
+    use rustafarian_shared::messages::general_messages::ServerType;
+
+    use crate::content_server::{ContentServer, ContentServerOptions};
+    use crate::content_store::ContentStore;
+    use crate::error::ContentServerError;
+    use crate::selection::SelectionPolicy;
+    use crate::tests::utils::temp_content_dir;
+
+    fn text_server(
+        directory: &Path,
+        server_type: ServerType,
+        options: ContentServerOptions,
+    ) -> Result<ContentServer, ContentServerError> {
+        ContentServer::with_options(
+            1,
+            HashMap::new(),
+            unbounded().1,
+            unbounded().1,
+            unbounded().0,
+            directory.to_str().unwrap(),
+            "media",
+            server_type,
+            false,
+            options,
+        )
+    }
+
+    #[test]
+    fn server_error_directory_test() {
+        let parent = temp_content_dir("server_error_missing");
+        let directory = parent.join("missing");
+        let result = text_server(
+            &directory,
+            ServerType::Text,
+            ContentServerOptions::default(),
+        );
+        let Err(ContentServerError::DirectoryMissing(missing)) = result else {
+            panic!("Unexpected result");
+        };
+        assert_eq!(missing, directory);
+
+        // A chat server has no content to be hybrid with
+        let options = ContentServerOptions {
+            hybrid: true,
+            ..ContentServerOptions::default()
+        };
+        assert!(matches!(
+            text_server(&directory, ServerType::Chat, options),
+            Err(ContentServerError::UnsupportedType(_))
+        ));
+    }
+
+    #[test]
+    fn server_error_content_test() {
+        let directory = temp_content_dir("server_error_names");
+        fs::write(directory.join("1.txt"), "One").unwrap();
+        fs::write(directory.join("notes.txt"), "Not an id").unwrap();
+        // A stray file is skipped, unless the names are checked strictly
+        let server = text_server(
+            &directory,
+            ServerType::Text,
+            ContentServerOptions::default(),
+        )
+        .expect("Failed to create the server");
+        assert_eq!(server.files.list(), vec![1]);
+        let options = ContentServerOptions {
+            strict_file_names: true,
+            ..ContentServerOptions::default()
+        };
+        let result = text_server(&directory, ServerType::Text, options);
+        let Err(ContentServerError::InvalidFileName(path)) = result else {
+            panic!("Unexpected result");
+        };
+        assert_eq!(path, directory.join("notes.txt"));
+
+        // An empty directory is refused even if uploads could fill it
+        let directory = temp_content_dir("server_error_empty");
+        let options = ContentServerOptions {
+            accept_uploads: true,
+            ..ContentServerOptions::default()
+        };
+        let result = text_server(&directory, ServerType::Text, options);
+        let Err(ContentServerError::NoContent(empty)) = result else {
+            panic!("Unexpected result");
+        };
+        assert_eq!(empty, *directory);
+    }
+
+    #[test]
+    fn server_error_rescan_test() {
+        let directory = temp_content_dir("server_error_rescan");
+        fs::write(directory.join("1.txt"), "One").unwrap();
+        let options = ContentServerOptions {
+            file_selection: SelectionPolicy::All,
+            ..ContentServerOptions::default()
+        };
+        let mut server = text_server(&directory, ServerType::Text, options.clone())
+            .expect("Failed to create the server");
+
+        // By default a rescan skips a file without an id
+        fs::write(directory.join("2.txt"), "Two").unwrap();
+        fs::write(directory.join("notes.txt"), "Not an id").unwrap();
+        let changes = server.reload_content().unwrap();
+        assert_eq!(changes.added, vec![2]);
+
+        // A strict rescan keeps the current content
+        server.options.strict_file_names = true;
+        fs::write(directory.join("3.txt"), "Three").unwrap();
+        assert_eq!(server.reload_content(), None);
+        assert_eq!(server.files.list(), vec![1, 2]);
+
+        fs::remove_file(directory.join("notes.txt")).unwrap();
+        let changes = server.reload_content().unwrap();
+        assert_eq!(changes.added, vec![3]);
+    }
+}
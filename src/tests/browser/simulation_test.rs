@@ -0,0 +1,157 @@
+#[cfg(test)]
+#[allow(unused)]
+pub mod simulation_test {
+    use std::collections::HashMap;
+    use std::fs;
+    use std::time::Duration;
+
+    use crossbeam_channel::unbounded;
+    use rustafarian_shared::messages::general_messages::ServerType;
+    use wg_2024::config::Config;
+    use wg_2024::network::SourceRoutingHeader;
+    use wg_2024::packet::{Fragment, NackType, Packet, PacketType, FRAGMENT_DSIZE};
+
+    use crate::config::ContentServerConfig;
+    use crate::error::ContentServerError;
+    use crate::messages::ContentKind;
+    use crate::selection::SelectionPolicy;
+    use crate::simulation::{Network, StandInDrone};
+    use crate::tests::utils::temp_content_dir;
+
+    const NETWORK: &str = r"
+        [[drone]]
+        id = 10
+        connected_node_ids = [11, 20, 1, 2]
+        pdr = 0.0
+
+        [[drone]]
+        id = 11
+        connected_node_ids = [10, 1, 2]
+        pdr = 0.0
+
+        [[client]]
+        id = 20
+        connected_drone_ids = [10]
+
+        [[server]]
+        id = 1
+        connected_drone_ids = [10, 11]
+
+        [[server]]
+        id = 2
+        connected_drone_ids = [10, 11]
+    ";
+
+    #[test]
+    fn simulation_browse_test() {
+        let directory = temp_content_dir("simulation_browse");
+        for (id, text) in [(1, "One"), (2, "Two"), (3, "Three")] {
+            fs::write(directory.join(format!("{id}.txt")), text).unwrap();
+        }
+        let mut text = ContentServerConfig {
+            server_type: ServerType::Text,
+            file_directory: directory.to_str().unwrap().to_string(),
+            ..ContentServerConfig::default()
+        };
+        text.options.file_selection = SelectionPolicy::All;
+        let chat = ContentServerConfig {
+            server_type: ServerType::Chat,
+            ..ContentServerConfig::default()
+        };
+        let config: Config = toml::from_str(NETWORK).unwrap();
+        let mut network = Network::start(&config, &HashMap::from([(1, text), (2, chat)]), None)
+            .expect("Failed to start the network");
+        // The clients stop discovering once both servers are found,
+        // the timeout only bounds a run where a response is lost
+        for client in &mut network.clients {
+            client.timeout = Duration::from_secs(10);
+        }
+
+        let visits = network.browse();
+        assert_eq!(network.shutdown(), Ok(()));
+        assert_eq!(visits.len(), 1);
+        let (client_id, visits) = &visits[0];
+        assert_eq!(*client_id, 20);
+        assert_eq!(visits.len(), 2);
+        assert_eq!(visits[0].server_id, 1);
+        assert_eq!(visits[0].kinds, vec![ContentKind::Text]);
+        assert_eq!(visits[0].files, vec![1, 2, 3]);
+        assert_eq!(visits[0].first_file, Some((1, 3)));
+        assert_eq!(visits[1].server_id, 2);
+        assert!(visits[1].is_chat);
+        assert_eq!(visits[1].clients, vec![20]);
+    }
+
+    #[test]
+    fn simulation_unknown_node_test() {
+        let mut config: Config = toml::from_str(NETWORK).unwrap();
+        config.client[0].connected_drone_ids.push(30);
+        assert!(matches!(
+            Network::start(&config, &HashMap::new(), None),
+            Err(ContentServerError::InvalidConfig(_))
+        ));
+    }
+
+    #[test]
+    fn simulation_drone_test() {
+        let client = unbounded();
+        let server = unbounded();
+        let mut drone = StandInDrone::new(
+            10,
+            unbounded().1,
+            HashMap::from([(20, client.0), (1, server.0)]),
+            1.0,
+            None,
+        );
+        let fragment = Packet::new_fragment(
+            SourceRoutingHeader::new(vec![20, 10, 1], 1),
+            5,
+            Fragment::new(0, 1, [0; FRAGMENT_DSIZE]),
+        );
+
+        // Every fragment is dropped, the client is told with a NACK
+        drone.handle_packet(fragment);
+        assert!(server.1.try_recv().is_err());
+        let nack = client.1.try_recv().expect("No NACK sent");
+        assert_eq!(nack.routing_header.hops, vec![10, 20]);
+        let PacketType::Nack(nack) = nack.pack_type else {
+            panic!("Unexpected packet");
+        };
+        assert_eq!(nack.nack_type, NackType::Dropped);
+
+        // Acks are never dropped
+        let ack = Packet::new_ack(SourceRoutingHeader::new(vec![1, 10, 20], 1), 5, 0);
+        drone.handle_packet(ack);
+        assert!(matches!(
+            client.1.try_recv().map(|packet| packet.pack_type),
+            Ok(PacketType::Ack(_))
+        ));
+    }
+
+    #[test]
+    fn simulation_seeded_drone_test() {
+        // Drones with the same seed and id drop the same fragments
+        let dropped = |seed| {
+            let client = unbounded();
+            let mut drone = StandInDrone::new(
+                10,
+                unbounded().1,
+                HashMap::from([(20, client.0), (1, unbounded().0)]),
+                0.5,
+                Some(seed),
+            );
+            (0..32)
+                .map(|index| {
+                    drone.handle_packet(Packet::new_fragment(
+                        SourceRoutingHeader::new(vec![20, 10, 1], 1),
+                        5,
+                        Fragment::new(index, 32, [0; FRAGMENT_DSIZE]),
+                    ));
+                    client.1.try_recv().is_ok()
+                })
+                .collect::<Vec<bool>>()
+        };
+        assert_eq!(dropped(7), dropped(7));
+        assert_ne!(dropped(7), dropped(8));
+    }
+}
@@ -0,0 +1,195 @@
+#[cfg(test)]
+#[allow(unused)]
+pub mod streaming_test {
+    use std::fs;
+    use std::io::{Cursor, Read};
+
+    use encoding_rs::WINDOWS_1252;
+    use rustafarian_shared::assembler::assembler::Assembler;
+    use rustafarian_shared::messages::{
+        browser_messages::{
+            BrowserRequest, BrowserRequestWrapper, BrowserResponse, BrowserResponseWrapper,
+        },
+        general_messages::{DroneSend, ServerType},
+    };
+    use wg_2024::network::SourceRoutingHeader;
+    use wg_2024::packet::{Ack, Packet, PacketType};
+
+    use crate::content_server::ContentServerOptions;
+    use crate::content_store::MemoryStore;
+    use crate::messages::{ContentError, ContentResponse, ContentResponseWrapper};
+    use crate::stream::{Payload, ResponseStream, Source};
+    use crate::tests::utils::{
+        build_server_in, build_server_with_stores, send_request, temp_content_dir,
+    };
+
+    fn read_all(mut stream: ResponseStream) -> String {
+        let mut serialized = Vec::new();
+        loop {
+            let bytes = stream.next_bytes(7).unwrap();
+            if bytes.is_empty() {
+                break;
+            }
+            serialized.extend(bytes);
+        }
+        String::from_utf8(serialized).unwrap()
+    }
+
+    fn source(data: Vec<u8>) -> Box<dyn Source> {
+        Box::new(Cursor::new(data))
+    }
+
+    #[test]
+    fn streaming_serialization_test() {
+        let text = "Quote \" backslash \\ tab\t new\nline \u{1} café ✓".repeat(50);
+        let expected =
+            BrowserResponseWrapper::Chat(BrowserResponse::TextFile(3, text.clone())).stringify();
+        let template =
+            BrowserResponseWrapper::Chat(BrowserResponse::TextFile(3, String::new())).stringify();
+        let (prefix, suffix) = ResponseStream::split_response(&template, "\"\"").unwrap();
+        let stream =
+            ResponseStream::new(&prefix, &suffix, Payload::Text, source(text.into_bytes()))
+                .unwrap();
+        assert_eq!(stream.len(), expected.len() as u64);
+        assert_eq!(read_all(stream), expected);
+
+        let data: Vec<u8> = (0..=255).cycle().take(3000).collect();
+        let expected =
+            BrowserResponseWrapper::Chat(BrowserResponse::MediaFile(4, data.clone())).stringify();
+        let template =
+            BrowserResponseWrapper::Chat(BrowserResponse::MediaFile(4, Vec::new())).stringify();
+        let (prefix, suffix) = ResponseStream::split_response(&template, "[]").unwrap();
+        let stream = ResponseStream::new(&prefix, &suffix, Payload::Bytes, source(data)).unwrap();
+        assert_eq!(stream.len(), expected.len() as u64);
+        assert_eq!(read_all(stream), expected);
+    }
+
+    #[test]
+    fn streaming_changed_source_test() {
+        let directory = temp_content_dir("streaming_changed");
+        let path = directory.join("1.txt");
+        fs::write(&path, "A line that will be cut. ".repeat(20)).unwrap();
+        let template =
+            BrowserResponseWrapper::Chat(BrowserResponse::TextFile(1, String::new())).stringify();
+        let (prefix, suffix) = ResponseStream::split_response(&template, "\"\"").unwrap();
+        let source = Box::new(fs::File::open(&path).unwrap());
+        let mut stream = ResponseStream::new(&prefix, &suffix, Payload::Text, source).unwrap();
+
+        // The file shrinks after the length of the response was measured
+        fs::write(&path, "Cut").unwrap();
+        let error = std::iter::repeat_with(|| stream.next_bytes(128))
+            .take(100)
+            .find_map(Result::err)
+            .expect("The change was not noticed");
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn streaming_source_error_test() {
+        let directory = temp_content_dir("streaming_source_error");
+        let path = directory.join("1.txt");
+        // Bigger than the buffer of the file reader
+        fs::write(&path, "A line that will be cut. ".repeat(2000)).unwrap();
+        let options = ContentServerOptions {
+            stream_threshold_bytes: 100,
+            stream_window: 4,
+            ..ContentServerOptions::default()
+        };
+        let (mut server, neighbor) = build_server_in(&directory, ServerType::Text, options);
+
+        let request = BrowserRequestWrapper::Chat(BrowserRequest::TextFileRequest(1));
+        send_request(&mut server, &request.stringify(), 8);
+        // The file shrinks while it's streamed
+        fs::write(&path, "Cut").unwrap();
+
+        let mut assembler = Assembler::new();
+        let mut error = None;
+        while error.is_none() {
+            let sent: Vec<Packet> = neighbor
+                .1
+                .try_iter()
+                .filter(|packet| matches!(packet.pack_type, PacketType::MsgFragment(_)))
+                .collect();
+            assert!(!sent.is_empty(), "The stream stalled without an error");
+            for packet in sent {
+                let PacketType::MsgFragment(fragment) = packet.pack_type else {
+                    continue;
+                };
+                if packet.session_id != 8 {
+                    // The error is sent under a new session, not mixed with the cut response
+                    let message = assembler.add_fragment(fragment, packet.session_id);
+                    error = message.map(|message| String::from_utf8(message).unwrap());
+                    continue;
+                }
+                let ack = Packet {
+                    pack_type: PacketType::Ack(Ack {
+                        fragment_index: fragment.fragment_index,
+                    }),
+                    session_id: packet.session_id,
+                    routing_header: SourceRoutingHeader::new(vec![21, 2, 1], 2),
+                };
+                server.handle_drone_packets(Ok(ack));
+            }
+        }
+
+        match ContentResponseWrapper::from_string(&error.unwrap()) {
+            Ok(ContentResponseWrapper::Content(ContentResponse::Error(
+                ContentError::InternalError(message),
+            ))) => assert!(message.contains("'1'")),
+            _ => panic!("Unexpected response"),
+        }
+        assert!(!server.sent_packets.contains_key(&8));
+    }
+
+    #[test]
+    fn streaming_window_test() {
+        let text = "A long line of text to stream. ".repeat(100);
+        let mut files = MemoryStore::new();
+        files.insert(1, text.clone());
+        files.detect_encodings(WINDOWS_1252);
+        let (mut server, neighbor) = build_server_with_stores(
+            Box::new(files),
+            Box::new(MemoryStore::new()),
+            ServerType::Text,
+        );
+        server.options.stream_threshold_bytes = 100;
+        server.options.stream_window = 4;
+
+        let request = BrowserRequestWrapper::Chat(BrowserRequest::TextFileRequest(1));
+        send_request(&mut server, &request.stringify(), 8);
+
+        let mut assembler = Assembler::new();
+        let mut message = None;
+        let mut fragments = 0;
+        while message.is_none() {
+            // Only the window is sent before the acks
+            let sent: Vec<Packet> = neighbor
+                .1
+                .try_iter()
+                .filter(|packet| matches!(packet.pack_type, PacketType::MsgFragment(_)))
+                .collect();
+            assert!(!sent.is_empty(), "The stream stalled");
+            assert!(sent.len() <= 4);
+            for packet in sent {
+                let PacketType::MsgFragment(fragment) = packet.pack_type else {
+                    continue;
+                };
+                fragments += 1;
+                let ack = Packet {
+                    pack_type: PacketType::Ack(Ack {
+                        fragment_index: fragment.fragment_index,
+                    }),
+                    session_id: packet.session_id,
+                    routing_header: SourceRoutingHeader::new(vec![21, 2, 1], 2),
+                };
+                message = assembler.add_fragment(fragment, packet.session_id);
+                server.handle_drone_packets(Ok(ack));
+            }
+        }
+
+        let expected = BrowserResponseWrapper::Chat(BrowserResponse::TextFile(1, text)).stringify();
+        assert_eq!(String::from_utf8(message.unwrap()).unwrap(), expected);
+        assert!(fragments > 4);
+        assert!(server.sent_packets.is_empty());
+    }
+}
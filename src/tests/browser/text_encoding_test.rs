@@ -0,0 +1,113 @@
+#[cfg(test)]
+#[allow(unused)]
+pub mod text_encoding_test {
+    use crossbeam_channel::Receiver;
+    use encoding_rs::{UTF_16LE, WINDOWS_1252};
+    use rustafarian_shared::messages::{
+        browser_messages::{
+            BrowserRequest, BrowserRequestWrapper, BrowserResponse, BrowserResponseWrapper,
+        },
+        general_messages::{DroneSend, ServerType},
+    };
+    use wg_2024::packet::Packet;
+
+    use crate::content_server::ContentServer;
+    use crate::content_store::{ContentStore, MemoryStore};
+    use crate::tests::utils::{build_server_with_stores, receive_message, send_request};
+    use crate::text;
+
+    fn request_text(
+        server: &mut ContentServer,
+        neighbor_receiver: &Receiver<Packet>,
+        id: u8,
+    ) -> String {
+        let request = BrowserRequestWrapper::Chat(BrowserRequest::TextFileRequest(id));
+        send_request(server, &request.stringify(), u64::from(id));
+
+        let response = receive_message(neighbor_receiver).expect("No response from the server");
+        let response: BrowserResponseWrapper =
+            serde_json::from_slice(&response).expect("Error deserializing the response");
+        match response {
+            BrowserResponseWrapper::Chat(BrowserResponse::TextFile(_, content)) => content,
+            _ => panic!("Unexpected response"),
+        }
+    }
+
+    #[test]
+    fn text_encoding_transcode_test() {
+        let mut files = MemoryStore::new();
+        // Windows-1252 without byte order mark
+        files.insert(1, b"caf\xe9".to_vec());
+        // UTF-16LE with byte order mark
+        files.insert(2, b"\xff\xfec\x00a\x00f\x00\xe9\x00".to_vec());
+        // UTF-8 with byte order mark
+        files.insert(3, b"\xef\xbb\xbfcaf\xc3\xa9".to_vec());
+        let (mut server, neighbor) = build_server_with_stores(
+            Box::new(files),
+            Box::new(MemoryStore::new()),
+            ServerType::Text,
+        );
+
+        for id in 1..=3 {
+            assert_eq!(request_text(&mut server, &neighbor.1, id), "café");
+        }
+    }
+
+    #[test]
+    fn text_encoding_detect_test() {
+        let mut files = MemoryStore::new();
+        files.insert(1, b"caf\xe9".to_vec());
+        files.insert(2, b"\xff\xfec\x00a\x00f\x00\xe9\x00".to_vec());
+        files.insert(3, "café");
+        files.detect_encodings(WINDOWS_1252);
+
+        let encoding = |id| files.metadata(id).unwrap().encoding.unwrap();
+        assert_eq!(encoding(1), "windows-1252");
+        assert_eq!(encoding(2), "UTF-16LE");
+        assert_eq!(encoding(3), "UTF-8");
+    }
+
+    #[test]
+    fn text_encoding_chunks_test() {
+        // Characters split between two chunks of the reader are still valid UTF-8
+        let valid = "é".repeat(10_000);
+        assert_eq!(
+            text::read_encoding(valid.as_bytes(), WINDOWS_1252)
+                .unwrap()
+                .name(),
+            "UTF-8"
+        );
+        let invalid = [valid.as_bytes(), b"\xe9"].concat();
+        assert_eq!(
+            text::read_encoding(invalid.as_slice(), WINDOWS_1252)
+                .unwrap()
+                .name(),
+            "windows-1252"
+        );
+
+        let data = b"caf\xe9\n".repeat(5_000);
+        let mut lines = Vec::new();
+        text::decode_lines(data.as_slice(), WINDOWS_1252, |chunk| {
+            assert!(chunk.ends_with('\n'));
+            lines.extend(chunk.lines().map(str::to_string));
+        })
+        .unwrap();
+        assert_eq!(lines.len(), 5_000);
+        assert!(lines.iter().all(|line| line == "caf\u{e9}"));
+    }
+
+    #[test]
+    fn text_encoding_fallback_option_test() {
+        let mut files = MemoryStore::new();
+        // "Привет" in KOI8-R
+        files.insert(1, b"\xf0\xd2\xc9\xd7\xc5\xd4".to_vec());
+        let (mut server, neighbor) = build_server_with_stores(
+            Box::new(files),
+            Box::new(MemoryStore::new()),
+            ServerType::Text,
+        );
+        server.options.text_fallback_encoding = "koi8-r".to_string();
+
+        assert_eq!(request_text(&mut server, &neighbor.1, 1), "Привет");
+    }
+}
@@ -0,0 +1,109 @@
+#[cfg(test)]
+#[allow(unused)]
+pub mod upload_test {
+    use std::fs;
+
+    use crossbeam_channel::Receiver;
+    use image::{DynamicImage, ImageFormat, RgbImage};
+    use rustafarian_shared::messages::{
+        browser_messages::{
+            BrowserRequest, BrowserRequestWrapper, BrowserResponse, BrowserResponseWrapper,
+        },
+        general_messages::{DroneSend, ServerType},
+    };
+    use wg_2024::packet::Packet;
+
+    use crate::content_server::{ContentServer, ContentServerOptions};
+    use crate::content_store::{ContentStore, MemoryStore};
+    use crate::media;
+    use crate::messages::{ContentRequest, ContentResponse};
+    use crate::selection::SelectionPolicy;
+    use crate::tests::utils::{
+        build_server_in, build_server_with_stores, receive_message, request_content, send_request,
+        temp_content_dir,
+    };
+
+    fn upload(
+        server: &mut ContentServer,
+        neighbor_receiver: &Receiver<Packet>,
+        data: Vec<u8>,
+        session_id: u64,
+    ) -> ContentResponse {
+        let request = ContentRequest::Upload { data, kind: None };
+        request_content(server, neighbor_receiver, request, session_id)
+    }
+
+    #[test]
+    fn upload_text_test() {
+        let mut files = MemoryStore::new();
+        files.insert(0, "Zero");
+        files.insert(1, "One");
+        let (mut server, neighbor) = build_server_with_stores(
+            Box::new(files),
+            Box::new(MemoryStore::new()),
+            ServerType::Text,
+        );
+        // Uploads are refused unless the server accepts them
+        let response = upload(&mut server, &neighbor.1, b"Shared".to_vec(), 6);
+        assert!(matches!(response, ContentResponse::UploadRejected(_)));
+        server.options.accept_uploads = true;
+        server.options.max_upload_bytes = 16;
+
+        let response = upload(&mut server, &neighbor.1, b"Shared by a client".to_vec(), 1);
+        assert!(matches!(response, ContentResponse::UploadRejected(_)));
+        let response = upload(&mut server, &neighbor.1, b"caf\xe9".to_vec(), 2);
+        assert!(matches!(response, ContentResponse::UploadRejected(_)));
+        let response = upload(&mut server, &neighbor.1, Vec::new(), 3);
+        assert!(matches!(response, ContentResponse::UploadRejected(_)));
+
+        let response = upload(&mut server, &neighbor.1, b"Shared text".to_vec(), 4);
+        assert_eq!(response, ContentResponse::Uploaded(2));
+
+        let request = BrowserRequestWrapper::Chat(BrowserRequest::TextFileRequest(2));
+        send_request(&mut server, &request.stringify(), 5);
+        let response = receive_message(&neighbor.1).expect("No response from the server");
+        let response: BrowserResponseWrapper =
+            serde_json::from_slice(&response).expect("Error deserializing the response");
+        match response {
+            BrowserResponseWrapper::Chat(BrowserResponse::TextFile(id, content)) => {
+                assert_eq!(id, 2);
+                assert_eq!(content, "Shared text");
+            }
+            _ => panic!("Unexpected response"),
+        }
+    }
+
+    #[test]
+    fn upload_media_directory_test() {
+        let directory = temp_content_dir("upload_media");
+        let image = DynamicImage::ImageRgb8(RgbImage::new(4, 4));
+        let png = media::encode(&image, ImageFormat::Png).unwrap();
+        fs::write(directory.join("0.png"), &png).unwrap();
+        fs::write(directory.join("1.png"), &png).unwrap();
+
+        let options = ContentServerOptions {
+            media_selection: SelectionPolicy::Ids(vec![1]),
+            accept_uploads: true,
+            ..ContentServerOptions::default()
+        };
+        let (mut server, neighbor) = build_server_in(&directory, ServerType::Media, options);
+
+        let response = upload(&mut server, &neighbor.1, b"not an image".to_vec(), 1);
+        assert!(matches!(response, ContentResponse::UploadRejected(_)));
+
+        // 0.png is on disk but not served, so it's not overwritten
+        let gif = media::encode(&image, ImageFormat::Gif).unwrap();
+        let response = upload(&mut server, &neighbor.1, gif.clone(), 2);
+        assert_eq!(response, ContentResponse::Uploaded(2));
+        assert_eq!(fs::read(directory.join("0.png")).unwrap(), png);
+        assert_eq!(fs::read(directory.join("0002.gif")).unwrap(), gif);
+
+        // Uploads are kept by a rescan with the same id, even if the policy does not select them
+        let changes = server.reload_content().unwrap();
+        assert!(changes.is_empty());
+        assert_eq!(server.media.list(), vec![1, 2]);
+        let path = server.media.metadata(2).unwrap().path.unwrap();
+        assert_eq!(path, directory.join("0002.gif"));
+        assert_eq!(server.media.get(2).unwrap(), gif);
+    }
+}
@@ -0,0 +1,143 @@
+#[cfg(test)]
+#[allow(unused)]
+pub mod versioning_test {
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    use crossbeam_channel::Receiver;
+    use rustafarian_shared::messages::general_messages::ServerType;
+    use wg_2024::packet::Packet;
+
+    use crate::content_server::{ContentServer, ContentServerOptions};
+    use crate::content_store::sha256_hex;
+    use crate::history::{ContentHistory, INDEX_FILE};
+    use crate::messages::{ContentError, ContentRequest, ContentResponse, VersionedFile};
+    use crate::selection::SelectionPolicy;
+    use crate::tests::utils::{build_server_in, request_content, temp_content_dir};
+
+    fn text_server(
+        directory: &Path,
+        history_directory: Option<PathBuf>,
+    ) -> (ContentServer, Receiver<Packet>) {
+        let options = ContentServerOptions {
+            file_selection: SelectionPolicy::All,
+            history_directory,
+            ..ContentServerOptions::default()
+        };
+        let (server, neighbor) = build_server_in(directory, ServerType::Text, options);
+        (server, neighbor.1)
+    }
+
+    #[test]
+    fn versioning_history_test() {
+        let directory = temp_content_dir("versioning");
+        fs::write(directory.join("1.txt"), "First draft").unwrap();
+        fs::write(directory.join("2.txt"), "Unchanged").unwrap();
+        let archive = temp_content_dir("versioning_archive");
+        let (mut server, neighbor) = text_server(&directory, Some(archive.to_path_buf()));
+        // The files found when the server starts are not copied
+        assert!(!archive.join("1.1").exists());
+
+        // The versions that follow a change are archived
+        fs::write(directory.join("1.txt"), "Second draft").unwrap();
+        server.reload_content();
+        fs::write(directory.join("1.txt"), "Third draft").unwrap();
+        server.reload_content();
+        assert!(archive.join("1.2").exists());
+
+        let response = request_content(
+            &mut server,
+            &neighbor,
+            ContentRequest::VersionedFileList { kind: None },
+            1,
+        );
+        assert_eq!(
+            response,
+            ContentResponse::VersionedFileList(vec![
+                VersionedFile {
+                    id: 1,
+                    version: 3,
+                    versions: vec![2, 3],
+                },
+                VersionedFile {
+                    id: 2,
+                    version: 1,
+                    versions: vec![1],
+                },
+            ])
+        );
+
+        let old = ContentRequest::FileVersion {
+            id: 1,
+            version: Some(2),
+            kind: None,
+        };
+        let ContentResponse::FileVersion { version, data, .. } =
+            request_content(&mut server, &neighbor, old, 2)
+        else {
+            panic!("Unexpected response");
+        };
+        assert_eq!((version, data), (2, b"Second draft".to_vec()));
+        let first = ContentRequest::FileVersion {
+            id: 1,
+            version: Some(1),
+            kind: None,
+        };
+        assert_eq!(
+            request_content(&mut server, &neighbor, first, 4),
+            ContentResponse::Error(ContentError::NotFound(1))
+        );
+
+        let latest = ContentRequest::FileVersion {
+            id: 1,
+            version: None,
+            kind: None,
+        };
+        let ContentResponse::FileVersion { version, data, .. } =
+            request_content(&mut server, &neighbor, latest, 3)
+        else {
+            panic!("Unexpected response");
+        };
+        assert_eq!((version, data), (3, b"Third draft".to_vec()));
+
+        // The archive survives a restart of the server, the digests come from its index
+        drop(server);
+        assert!(archive.join(INDEX_FILE).exists());
+        let (server, _neighbor) = text_server(&directory, Some(archive.to_path_buf()));
+        assert_eq!(server.history.current_version(1), Some(3));
+        assert_eq!(server.history.versions(1), vec![2, 3]);
+        assert_eq!(server.history.get(1, 2).unwrap(), b"Second draft");
+    }
+
+    #[test]
+    fn versioning_in_memory_test() {
+        let directory = temp_content_dir("versioning_memory");
+        fs::write(directory.join("1.txt"), "First draft").unwrap();
+        let (mut server, _neighbor) = text_server(&directory, None);
+        fs::write(directory.join("1.txt"), "Second draft").unwrap();
+        server.reload_content();
+
+        // Only the numbers are kept, the latest version is the file itself
+        assert_eq!(server.history.current_version(1), Some(2));
+        assert_eq!(server.history.versions(1), vec![2]);
+        assert!(server.history.get(1, 1).is_err());
+        assert_eq!(fs::read_dir(&directory).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn versioning_limit_test() {
+        let archive = temp_content_dir("versioning_limit");
+        let mut history = ContentHistory::in_directory(&archive, 2);
+        for text in ["one", "two", "two", "three"] {
+            let data = text.as_bytes().to_vec();
+            history
+                .record(4, &sha256_hex(&data), || Ok(data.as_slice()))
+                .unwrap();
+        }
+        assert_eq!(history.current_version(4), Some(3));
+        assert_eq!(history.versions(4), vec![2, 3]);
+        assert!(history.get(4, 1).is_err());
+        assert_eq!(history.get(4, 2).unwrap(), b"two");
+        assert!(!archive.join("4.1").exists());
+    }
+}
@@ -1,14 +1,29 @@
 #[allow(unused)]
 use std::collections::HashMap;
 
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
 use crossbeam_channel::{unbounded, Receiver, Sender};
-use rustafarian_shared::messages::{
-    commander_messages::{SimControllerCommand, SimControllerResponseWrapper},
-    general_messages::ServerType,
+use rustafarian_shared::{
+    assembler::{assembler::Assembler, disassembler::Disassembler},
+    messages::{
+        commander_messages::{SimControllerCommand, SimControllerResponseWrapper},
+        general_messages::ServerType,
+    },
+};
+use wg_2024::{
+    network::SourceRoutingHeader,
+    packet::{Packet, PacketType},
 };
-use wg_2024::packet::Packet;
 
-use crate::content_server::ContentServer;
+use crate::builder::ContentServerBuilder;
+use crate::content_server::{ContentServer, ContentServerOptions};
+use crate::content_store::{ContentStore, MemoryStore};
+use crate::messages::{
+    ContentRequest, ContentRequestWrapper, ContentResponse, ContentResponseWrapper,
+};
 
 pub(crate) fn build_server() -> (
     ContentServer,
@@ -38,7 +53,8 @@ pub(crate) fn build_server() -> (
         "media",
         ServerType::Text,
         true,
-    );
+    )
+    .expect("Failed to create the server");
 
     server.topology.add_node(2);
     server.topology.add_node(21);
@@ -52,3 +68,166 @@ pub(crate) fn build_server() -> (
         controller_channel_messages,
     )
 }
+
+pub(crate) fn build_server_with_stores(
+    files: Box<dyn ContentStore>,
+    media: Box<dyn ContentStore>,
+    server_type: ServerType,
+) -> (ContentServer, (Sender<Packet>, Receiver<Packet>)) {
+    let neighbor: (Sender<Packet>, Receiver<Packet>) = unbounded();
+    let mut neighbors = HashMap::new();
+    neighbors.insert(2, neighbor.0.clone());
+    let channel: (Sender<Packet>, Receiver<Packet>) = unbounded();
+
+    let controller_channel_commands = unbounded();
+    let controller_channel_messages = unbounded();
+
+    let mut server = ContentServer::with_stores(
+        1,
+        neighbors,
+        channel.1,
+        controller_channel_commands.1,
+        controller_channel_messages.0,
+        files,
+        media,
+        server_type,
+        true,
+    );
+
+    server.topology.add_node(2);
+    server.topology.add_node(21);
+    server.topology.add_edge(2, 21);
+    server.topology.add_edge(1, 2);
+
+    (server, neighbor)
+}
+
+/// Builds a media server holding the encoded images under their ids and no text files,
+/// connected to client 21 through drone 2 like `build_server_with_stores`
+pub(crate) fn media_server_with(
+    images: Vec<(u8, Vec<u8>)>,
+) -> (ContentServer, (Sender<Packet>, Receiver<Packet>)) {
+    let mut media = MemoryStore::new();
+    for (id, data) in images {
+        media.insert(id, data);
+    }
+    build_server_with_stores(
+        Box::new(MemoryStore::new()),
+        Box::new(media),
+        ServerType::Media,
+    )
+}
+
+/// Builds a server that loads the content of its type from the directory, both kinds for a hybrid server,
+/// connected to client 21 through drone 2 like `build_server_with_stores`
+pub(crate) fn build_server_in(
+    directory: &Path,
+    server_type: ServerType,
+    options: ContentServerOptions,
+) -> (ContentServer, (Sender<Packet>, Receiver<Packet>)) {
+    let neighbor: (Sender<Packet>, Receiver<Packet>) = unbounded();
+    let directory = directory.to_str().unwrap();
+    let mut server = ContentServerBuilder::new(1)
+        .server_type(server_type)
+        .file_directory(directory)
+        .media_directory(directory)
+        .options(options)
+        .sender(2, neighbor.0.clone())
+        .receiver(unbounded().1)
+        .controller(unbounded().1, unbounded().0)
+        .build()
+        .expect("Failed to create the server");
+
+    server.topology.add_node(2);
+    server.topology.add_node(21);
+    server.topology.add_edge(2, 21);
+    server.topology.add_edge(1, 2);
+
+    (server, neighbor)
+}
+
+/// Sends a request to the server as if it came from client 21 through drone 2
+pub(crate) fn send_request(server: &mut ContentServer, request: &str, session_id: u64) {
+    let fragments =
+        Disassembler::new().disassemble_message(request.as_bytes().to_vec(), session_id);
+    for fragment in fragments {
+        let packet = Packet {
+            routing_header: SourceRoutingHeader::new(vec![21, 2, 1], 1),
+            session_id,
+            pack_type: PacketType::MsgFragment(fragment),
+        };
+        server.handle_drone_packets(Ok(packet));
+    }
+}
+
+/// Reassembles the next message sent by the server, skipping the other packets
+pub(crate) fn receive_message(neighbor: &Receiver<Packet>) -> Option<Vec<u8>> {
+    let mut assembler = Assembler::new();
+    while let Ok(packet) = neighbor.recv_timeout(Duration::from_millis(500)) {
+        if let PacketType::MsgFragment(fragment) = packet.pack_type {
+            if let Some(message) = assembler.add_fragment(fragment, packet.session_id) {
+                return Some(message);
+            }
+        }
+    }
+    None
+}
+
+/// Reassembles the next message sent by the server as a response of this crate
+/// # Panics
+/// Panics if no message arrives or it isn't a `ContentResponseWrapper`
+pub(crate) fn receive_response(neighbor: &Receiver<Packet>) -> ContentResponseWrapper {
+    let response = receive_message(neighbor).expect("No response from the server");
+    ContentResponseWrapper::from_string(&String::from_utf8(response).unwrap())
+        .expect("Error deserializing the response")
+}
+
+/// Sends a content request and returns the content response of the server
+/// # Panics
+/// Panics if the server doesn't answer with a `Content` response
+pub(crate) fn request_content(
+    server: &mut ContentServer,
+    neighbor: &Receiver<Packet>,
+    request: ContentRequest,
+    session_id: u64,
+) -> ContentResponse {
+    let request = ContentRequestWrapper::Content(request);
+    send_request(server, &request.stringify(), session_id);
+    let ContentResponseWrapper::Content(response) = receive_response(neighbor) else {
+        panic!("Unexpected response");
+    };
+    response
+}
+
+/// An empty directory for the content of a test, removed when it's dropped
+#[derive(Debug)]
+pub(crate) struct TempDir(PathBuf);
+
+impl AsRef<Path> for TempDir {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Creates an empty directory for the content of a test
+pub(crate) fn temp_content_dir(name: &str) -> TempDir {
+    let directory =
+        std::env::temp_dir().join(format!("content_server_{name}_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&directory);
+    std::fs::create_dir_all(&directory).expect("Failed to create the test directory");
+    TempDir(directory)
+}
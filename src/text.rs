@@ -0,0 +1,106 @@
+use encoding_rs::{Encoding, UTF_8, WINDOWS_1252};
+use log::error;
+use std::io::{self, Read};
+
+/// Size of the chunks read from a text that is not held in memory
+const CHUNK_SIZE: usize = 8 * 1024;
+
+/// Returns the encoding with that label (e.g. `latin1`, `utf-16le`, `shift_jis`),
+/// or Windows-1252, the usual encoding of legacy western text, if the label is unknown
+pub fn encoding_for_label(label: &str) -> &'static Encoding {
+    Encoding::for_label(label.as_bytes()).unwrap_or_else(|| {
+        error!("Unknown text encoding '{label}', using windows-1252\n");
+        WINDOWS_1252
+    })
+}
+
+/// Detects the encoding of a text: the one given by its byte order mark if it has one,
+/// UTF-8 if it's valid UTF-8, otherwise the fallback encoding
+pub fn detect_encoding(data: &[u8], fallback: &'static Encoding) -> &'static Encoding {
+    read_encoding(data, fallback).unwrap_or(fallback)
+}
+
+/// Detects the encoding of a text like `detect_encoding`, reading it in chunks
+/// # Errors
+/// Returns the error raised reading the text
+pub fn read_encoding(
+    mut reader: impl Read,
+    fallback: &'static Encoding,
+) -> io::Result<&'static Encoding> {
+    let mut pending = Vec::new();
+    (&mut reader).take(3).read_to_end(&mut pending)?;
+    if let Some((encoding, _)) = Encoding::for_bom(&pending) {
+        return Ok(encoding);
+    }
+    let mut chunk = vec![0; CHUNK_SIZE];
+    loop {
+        match std::str::from_utf8(&pending) {
+            Ok(_) => pending.clear(),
+            // A character split between two chunks, its end is in the next one
+            Err(err) if err.error_len().is_none() => {
+                pending.drain(..err.valid_up_to());
+            }
+            Err(_) => return Ok(fallback),
+        }
+        let read = reader.read(&mut chunk)?;
+        if read == 0 {
+            return Ok(if pending.is_empty() { UTF_8 } else { fallback });
+        }
+        pending.extend_from_slice(&chunk[..read]);
+    }
+}
+
+/// Transcodes a text in that encoding to UTF-8, removing the byte order mark,
+/// malformed sequences are replaced with U+FFFD
+pub fn decode(data: &[u8], encoding: &'static Encoding) -> String {
+    let (text, _, had_errors) = encoding.decode(data);
+    if had_errors {
+        error!(
+            "Warning: Malformed {} sequences replaced while decoding text\n",
+            encoding.name()
+        );
+    }
+    text.into_owned()
+}
+
+/// Transcodes a text in that encoding to UTF-8 like `decode`, reading it in chunks:
+/// `lines` is called with a few whole lines at a time, so the text is never held in memory
+/// # Errors
+/// Returns the error raised reading the text
+pub fn decode_lines(
+    mut reader: impl Read,
+    encoding: &'static Encoding,
+    mut lines: impl FnMut(&str),
+) -> io::Result<()> {
+    let mut decoder = encoding.new_decoder();
+    let mut chunk = vec![0; CHUNK_SIZE];
+    let mut text = String::new();
+    let mut had_errors = false;
+    loop {
+        let read = reader.read(&mut chunk)?;
+        let last = read == 0;
+        if let Some(length) = decoder.max_utf8_buffer_length(read) {
+            text.reserve(length);
+        }
+        let (_, _, errors) = decoder.decode_to_string(&chunk[..read], &mut text, last);
+        had_errors |= errors;
+        if last {
+            if !text.is_empty() {
+                lines(&text);
+            }
+            break;
+        }
+        // Keep the last line, it may go on in the next chunk
+        if let Some(end) = text.rfind('\n') {
+            lines(&text[..=end]);
+            text.drain(..=end);
+        }
+    }
+    if had_errors {
+        error!(
+            "Warning: Malformed {} sequences replaced while decoding text\n",
+            encoding.name()
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,72 @@
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, GenericImageView, ImageResult};
+
+use crate::media;
+
+/// Dimensions asked by the clients are rounded up to a multiple of this step
+pub const DIMENSION_STEP: u32 = 16;
+/// Qualities asked by the clients are rounded to a multiple of this step
+pub const QUALITY_STEP: u8 = 10;
+
+/// Identifies a rendition of a media file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct VariantKey {
+    pub id: u8,
+    pub max_dimension: u32,
+    pub quality: u8,
+}
+
+impl VariantKey {
+    /// Returns the key of the rendition served for a client request, so clients can only ask
+    /// for a few distinct renditions: the dimension is rounded up to a multiple of `DIMENSION_STEP`
+    /// and capped at `limit`, the quality is rounded to a multiple of `QUALITY_STEP`
+    pub fn quantized(id: u8, max_dimension: u32, quality: u8, limit: u32) -> VariantKey {
+        let limit = limit.max(1);
+        let max_dimension = max_dimension
+            .clamp(1, limit)
+            .div_ceil(DIMENSION_STEP)
+            .saturating_mul(DIMENSION_STEP)
+            .min(limit);
+        let quality = ((quality.clamp(1, 100) + QUALITY_STEP / 2) / QUALITY_STEP * QUALITY_STEP)
+            .max(QUALITY_STEP);
+        VariantKey {
+            id,
+            max_dimension,
+            quality,
+        }
+    }
+}
+
+/// A media file resized to fit a maximum dimension and encoded as JPEG
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Variant {
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>,
+}
+
+impl Variant {
+    /// Decodes the image and renders the variant described by the key,
+    /// images smaller than the maximum dimension are not enlarged
+    /// # Errors
+    /// Returns an error if the image can't be decoded or encoded
+    pub fn render(data: &[u8], key: VariantKey) -> ImageResult<Variant> {
+        let (image, _) = media::decode(data)?;
+        let (width, height) = image.dimensions();
+        let image = if width > key.max_dimension || height > key.max_dimension {
+            image.resize(key.max_dimension, key.max_dimension, FilterType::Triangle)
+        } else {
+            image
+        };
+        // JPEG has no alpha channel
+        let image = DynamicImage::ImageRgb8(image.to_rgb8());
+        let mut data = Vec::new();
+        JpegEncoder::new_with_quality(&mut data, key.quality).encode_image(&image)?;
+        Ok(Variant {
+            width: image.width(),
+            height: image.height(),
+            data,
+        })
+    }
+}